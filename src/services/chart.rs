@@ -0,0 +1,391 @@
+use crate::services::weather_service::{ForecastResponseData, ForecastTarget, WeatherService};
+use crate::utils::meteo;
+use crate::utils::output;
+use chrono::NaiveDate;
+use std::cmp::Ordering;
+use std::env;
+
+/*
+`weather chart` draws the days from today on, out of one request to the provider:
+
+== Forecast for Kyiv, UA from 2023-03-04 to 2023-03-06 ==
+Temperature, °C  ░ min..max  ● average
+   12 │                      ░░░░░░░░░░
+      │           ░░░░░░░░░░ ░░░░░░░░░░
+      │           ░░░░░░░░░░ ─────●░░░░
+      │           ░░░░─●────╱░░░░░░░░░░
+      │░░░░░░░░░░────╱░░░░░░ ░░░░░░░░░░
+      │░░░░░●───╱ ░░░░░░░░░░
+      │░░░░░░░░░░
+   -3 │░░░░░░░░░░
+Precipitation, mm
+ 11.0 │                         █████
+      │                         █████
+      │                         █████
+      │              ▇▇▇▇▇      █████
+   mm │    0.0        2.4       11.0
+    % │    60%        60%        60%
+ km/h │    ↗12        ↗12        ↗12
+      │  Sat 04     Sun 05     Mon 06
+
+Days share the width of the terminal (--width, or COLUMNS when it isn't a terminal).
+With --charset ascii the band is `:`, the line `-/\` with `o`, bars are `#` and
+wind is shown by compass points.
+*/
+
+pub const DEFAULT_DAYS: i64 = 7;
+const DEFAULT_WIDTH: usize = 80;
+// Values on the left and the axis.
+const LABEL: usize = 7;
+const MIN_COLUMN: usize = 3;
+const MAX_COLUMN: usize = 14;
+const TEMPERATURE_ROWS: usize = 8;
+const PRECIPITATION_ROWS: usize = 4;
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+struct Glyphs {
+    band: char,
+    flat: char,
+    rise: char,
+    fall: char,
+    dot: char,
+    axis: char,
+    degree: &'static str,
+}
+
+const UNICODE: Glyphs = Glyphs {
+    band: '░',
+    flat: '─',
+    rise: '╱',
+    fall: '╲',
+    dot: '●',
+    axis: '│',
+    degree: "°C",
+};
+
+const ASCII: Glyphs = Glyphs {
+    band: ':',
+    flat: '-',
+    rise: '/',
+    fall: '\\',
+    dot: 'o',
+    axis: '|',
+    degree: "C",
+};
+
+pub fn weather_chart(address: &str, days: i64, provider: WeatherService, width: Option<usize>) {
+    if !provider.has_forecast() {
+        return println!("Error: Provider unknown, please use: openweather, weatherapi or metno");
+    }
+    if days < 1 {
+        return println!("Error: Please ask for at least one day");
+    }
+    let width = width.unwrap_or_else(terminal_width);
+    if width < LABEL + MIN_COLUMN {
+        return println!("Error: Terminal is too narrow for a chart");
+    }
+    let forecasts = ForecastTarget::resolve(address, "today", None)
+        .and_then(|today| today.on_date(&format!("+{}d", days - 1)))
+        .and_then(|target| provider.forecast_days(&target));
+    match forecasts {
+        Ok(forecasts) if forecasts.is_empty() => println!("Error: No forecast for these days"),
+        Ok(forecasts) => println!("{}", render_chart(&forecasts, width, output::unicode())),
+        Err(error) => println!("Error: {error}"),
+    }
+}
+
+fn terminal_width() -> usize {
+    match ratatui::crossterm::terminal::size() {
+        Ok((columns, _)) if columns > 0 => columns as usize,
+        _ => env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+            .unwrap_or(DEFAULT_WIDTH),
+    }
+}
+
+/// Chart of the days as lines not wider than `width`, days which don't fit are left out.
+pub fn render_chart(days: &[ForecastResponseData], width: usize, unicode: bool) -> String {
+    let glyphs = match unicode {
+        true => &UNICODE,
+        false => &ASCII,
+    };
+    let room = width.saturating_sub(LABEL);
+    let days = &days[..days.len().min((room / MIN_COLUMN).max(1))];
+    let (first, last) = match (days.first(), days.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return String::new(),
+    };
+    let column = (room / days.len()).clamp(MIN_COLUMN, MAX_COLUMN);
+
+    let mut lines = vec![format!(
+        "== Forecast for {} from {} to {} ==",
+        first.location, first.date, last.date
+    )];
+    lines.push(format!(
+        "Temperature, {}  {} min..max  {} average",
+        glyphs.degree, glyphs.band, glyphs.dot
+    ));
+    lines.extend(temperature(days, column, glyphs));
+    if days.iter().any(|day| day.precip_mm.value.is_some()) {
+        lines.push("Precipitation, mm".to_string());
+        lines.extend(precipitation(days, column, glyphs, unicode));
+        lines.push(row("mm", glyphs.axis, days, column, |day| {
+            day.precip_mm.value.map(|mm| match format!("{mm:.1}") {
+                text if text.len() < column => text,
+                _ => format!("{mm:.0}"),
+            })
+        }));
+    }
+    if days.iter().any(|day| day.precip_chance.value.is_some()) {
+        lines.push(row("%", glyphs.axis, days, column, |day| {
+            day.precip_chance
+                .value
+                .map(|chance| format!("{chance:.0}%"))
+        }));
+    }
+    if days.iter().any(|day| day.maxwind_kph.value.is_some()) {
+        lines.push(row("km/h", glyphs.axis, days, column, |day| {
+            wind(day, unicode)
+        }));
+    }
+    lines.push(row("", glyphs.axis, days, column, |day| {
+        Some(day_name(&day.date, column))
+    }));
+
+    lines
+        .iter()
+        .map(|line| {
+            line.chars()
+                .take(width)
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Band from min to max of every day and a line through the averages at the middles of the days.
+fn temperature(days: &[ForecastResponseData], column: usize, glyphs: &Glyphs) -> Vec<String> {
+    let low = days
+        .iter()
+        .map(|day| day.min_temp)
+        .fold(f32::INFINITY, f32::min);
+    let high = days
+        .iter()
+        .map(|day| day.max_temp)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let span = (high - low).max(1.0);
+    let last_row = TEMPERATURE_ROWS - 1;
+    let row_of = |temp: f32| {
+        let row = ((high - temp) / span * last_row as f32).round();
+        row.clamp(0.0, last_row as f32) as usize
+    };
+    let middle = |index: usize| index * column + column / 2;
+
+    let mut grid = vec![vec![' '; days.len() * column]; TEMPERATURE_ROWS];
+    for (index, day) in days.iter().enumerate() {
+        for line in &mut grid[row_of(day.max_temp)..=row_of(day.min_temp)] {
+            for cell in &mut line[index * column..(index + 1) * column - 1] {
+                *cell = glyphs.band;
+            }
+        }
+    }
+    let averages: Vec<f32> = days
+        .iter()
+        .map(|day| {
+            day.avg_temp
+                .value
+                .unwrap_or((day.min_temp + day.max_temp) / 2.0)
+        })
+        .collect();
+    for (index, pair) in averages.windows(2).enumerate() {
+        let (from, to) = (middle(index), middle(index + 1));
+        let at = |x: usize| pair[0] + (pair[1] - pair[0]) * (x - from) as f32 / (to - from) as f32;
+        for x in from..to {
+            let (here, next) = (row_of(at(x)), row_of(at(x + 1)));
+            let (rows, glyph) = match here.cmp(&next) {
+                Ordering::Equal => (here..here + 1, glyphs.flat),
+                Ordering::Greater => (next + 1..here + 1, glyphs.rise),
+                Ordering::Less => (here..next, glyphs.fall),
+            };
+            for line in &mut grid[rows] {
+                line[x] = glyph;
+            }
+        }
+    }
+    for (index, average) in averages.iter().enumerate() {
+        grid[row_of(*average)][middle(index)] = glyphs.dot;
+    }
+
+    grid.iter()
+        .enumerate()
+        .map(|(row, cells)| {
+            let label = match row {
+                0 => format!("{high:.0}"),
+                row if row == last_row => format!("{low:.0}"),
+                _ => String::new(),
+            };
+            format!(
+                "{label:>5} {}{}",
+                glyphs.axis,
+                cells.iter().collect::<String>()
+            )
+        })
+        .collect()
+}
+
+// Bars grow by eighths of a row, or by half rows of `.` and `#` in ascii.
+fn precipitation(
+    days: &[ForecastResponseData],
+    column: usize,
+    glyphs: &Glyphs,
+    unicode: bool,
+) -> Vec<String> {
+    let most = days
+        .iter()
+        .filter_map(|day| day.precip_mm.value)
+        .fold(1.0, f32::max);
+    let bar = (column / 2).max(1);
+    (0..PRECIPITATION_ROWS)
+        .map(|row| {
+            let level = (PRECIPITATION_ROWS - 1 - row) * 8;
+            let cells: String = days
+                .iter()
+                .map(|day| {
+                    let eighths = day.precip_mm.value.map_or(0, |mm| {
+                        (mm / most * (PRECIPITATION_ROWS * 8) as f32).round() as usize
+                    });
+                    let glyph = match (eighths.saturating_sub(level).min(8), unicode) {
+                        (0, _) => ' ',
+                        (fill, true) => BLOCKS[fill - 1],
+                        (fill, false) if fill >= 4 => '#',
+                        _ => '.',
+                    };
+                    let start = column / 2 - bar / 2;
+                    format!(
+                        "{}{}{}",
+                        " ".repeat(start),
+                        glyph.to_string().repeat(bar),
+                        " ".repeat(column - start - bar)
+                    )
+                })
+                .collect();
+            let label = match row {
+                0 => format!("{most:.1}"),
+                _ => String::new(),
+            };
+            format!("{label:>5} {}{cells}", glyphs.axis)
+        })
+        .collect()
+}
+
+fn row(
+    label: &str,
+    axis: char,
+    days: &[ForecastResponseData],
+    column: usize,
+    value: impl Fn(&ForecastResponseData) -> Option<String>,
+) -> String {
+    let cells: String = days
+        .iter()
+        .map(|day| {
+            let text: String = value(day)
+                .unwrap_or_else(|| "-".to_string())
+                .chars()
+                .take(column - 1)
+                .collect();
+            format!("{text:^width$}", width = column)
+        })
+        .collect();
+    format!("{label:>5} {axis}{cells}")
+}
+
+fn wind(day: &ForecastResponseData, unicode: bool) -> Option<String> {
+    let speed = day.maxwind_kph.value?;
+    Some(match (day.wind_degree.value, unicode) {
+        (Some(degree), true) => format!("{}{speed:.0}", meteo::wind_arrow(degree)),
+        (Some(degree), false) => format!("{}{speed:.0}", meteo::compass(degree)),
+        (None, _) => format!("{speed:.0}"),
+    })
+}
+
+fn day_name(date: &str, column: usize) -> String {
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(date) if column > 6 => date.format("%a %d").to_string(),
+        Ok(date) => date.format("%d").to_string(),
+        Err(_) => date.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_chart;
+    use crate::services::condition::Condition;
+    use crate::services::reading::{Provenance, Reading};
+    use crate::services::weather_service::ForecastResponseData;
+
+    fn forecast(day: u32, min_temp: f32, max_temp: f32, precip_mm: f32) -> ForecastResponseData {
+        let reading = |value: f32| Reading::new(Some(value), Provenance::Forecast);
+        ForecastResponseData {
+            maxwind_kph: reading(12.0),
+            wind_degree: reading(225.0),
+            condition: Condition::Rain,
+            precip_mm: reading(precip_mm),
+            precip_chance: reading(60.0),
+            ..ForecastResponseData::sample(
+                "Kyiv, UA",
+                &format!("2023-03-{day:02}"),
+                min_temp,
+                max_temp,
+            )
+        }
+    }
+
+    fn week() -> Vec<ForecastResponseData> {
+        vec![
+            forecast(4, -3.0, 4.0, 0.0),
+            forecast(5, 1.0, 9.5, 2.4),
+            forecast(6, 4.0, 12.0, 11.0),
+            forecast(7, -1.0, 3.0, 0.3),
+            forecast(8, -8.0, -2.0, 0.0),
+            forecast(9, -5.0, 1.0, 5.0),
+            forecast(10, 0.0, 6.0, 0.0),
+        ]
+    }
+
+    #[test]
+    fn chart_fits_the_width() {
+        for width in [20, 40, 80, 200] {
+            for unicode in [true, false] {
+                let chart = render_chart(&week(), width, unicode);
+                assert!(chart.lines().all(|line| line.chars().count() <= width));
+            }
+        }
+    }
+
+    #[test]
+    fn narrow_chart_leaves_days_out() {
+        let chart = render_chart(&week(), 20, true);
+        assert!(chart.contains("04"));
+        assert!(!chart.contains("10"));
+    }
+
+    #[test]
+    fn ascii_chart_is_plain() {
+        let chart = render_chart(&week(), 80, false);
+        assert!(chart.is_ascii());
+        assert!(chart.contains("SW12"));
+        assert!(chart.contains("Sat 04"));
+        assert!(chart.contains('#'));
+    }
+
+    #[test]
+    fn unicode_chart_has_blocks_and_arrows() {
+        let chart = render_chart(&week(), 80, true);
+        assert!(chart.contains('█'));
+        assert!(chart.contains('●'));
+        assert!(chart.contains("↗12"));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::services::reading::{Provenance, Reading};
+use crate::services::weather_service::ForecastResponseData;
+use crate::utils::meteo;
+
+/*
+Comfort indices are computed here from the same values for every provider,
+so "feels like" of one provider is comparable to another one.
+Daily values are the extremes of the day: heat is taken at the max temperature,
+cold at the min temperature with the max wind.
+Feels like is the average temperature with the max wind too: it's the only wind every provider
+gives for a day, so on gusty days feels like is on the cold side.
+
+- heat index: NWS Rothfusz regression with Steadman's simple formula below 80°F;
+- wind chill: Environment Canada / NWS 2001 formula, for 10°C and below with wind from 4.8 Km/h;
+- humidex: Environment Canada, from the dew point, for 20°C and warmer;
+- apparent temperature: Steadman (Australian Bureau of Meteorology), shade version.
+*/
+
+const HEAT_INDEX_MIN_F: f32 = 80.0;
+const WIND_CHILL_MAX_C: f32 = 10.0;
+const WIND_CHILL_MIN_KPH: f32 = 4.8;
+const HUMIDEX_MIN_C: f32 = 20.0;
+const KPH_TO_MPS: f32 = 1.0 / 3.6;
+
+pub struct Comfort {
+    pub feels_like: Reading,
+    pub heat_index: Reading,
+    pub wind_chill: Reading,
+    pub humidex: Reading,
+}
+
+impl Comfort {
+    pub fn new(forecast: &ForecastResponseData) -> Self {
+        let humidity = forecast.avghumidity.value;
+        let wind_kph = forecast.maxwind_kph.value;
+        // Dew point barely changes during the day, unlike relative humidity.
+        let dew_point = forecast
+            .dew_point_c
+            .value
+            .or(match (forecast.avg_temp.value, humidity) {
+                (Some(temp), Some(humidity)) => meteo::dew_point(temp, humidity),
+                _ => None,
+            });
+        let derived = |value: Option<f32>| Reading::new(value, Provenance::Derived);
+
+        Self {
+            // Max wind, providers don't give an average one.
+            feels_like: derived(match (forecast.avg_temp.value, humidity, wind_kph) {
+                (Some(temp), Some(humidity), Some(wind_kph)) => {
+                    Some(apparent_temperature(temp, humidity, wind_kph))
+                }
+                _ => None,
+            }),
+            heat_index: derived(dew_point.and_then(|dew_point| {
+                heat_index(
+                    forecast.max_temp,
+                    meteo::relative_humidity(forecast.max_temp, dew_point),
+                )
+            })),
+            wind_chill: derived(
+                wind_kph.and_then(|wind_kph| wind_chill(forecast.min_temp, wind_kph)),
+            ),
+            humidex: derived(dew_point.and_then(|dew_point| humidex(forecast.max_temp, dew_point))),
+        }
+    }
+}
+
+/// Heat index in °C, only for 80°F (26.7°C) and hotter where it's defined.
+pub fn heat_index(temp_c: f32, humidity: f32) -> Option<f32> {
+    let t = temp_c * 9.0 / 5.0 + 32.0;
+    if t < HEAT_INDEX_MIN_F {
+        return None;
+    }
+    let rh = humidity;
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    let index = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut index = -42.379 + 2.049_015_3 * t + 10.143_332 * rh
+            - 0.224_755_4 * t * rh
+            - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            index -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            index += (rh - 85.0) / 10.0 * ((87.0 - t) / 5.0);
+        }
+        index
+    };
+    Some(meteo::round((index - 32.0) * 5.0 / 9.0))
+}
+
+/// Wind chill in °C, only for 10°C and colder with wind from 4.8 Km/h.
+pub fn wind_chill(temp_c: f32, wind_kph: f32) -> Option<f32> {
+    if temp_c > WIND_CHILL_MAX_C || wind_kph < WIND_CHILL_MIN_KPH {
+        return None;
+    }
+    let wind = wind_kph.powf(0.16);
+    Some(meteo::round(
+        13.12 + 0.6215 * temp_c - 11.37 * wind + 0.3965 * temp_c * wind,
+    ))
+}
+
+/// Humidex in °C, only for 20°C and warmer where Environment Canada reports it.
+pub fn humidex(temp_c: f32, dew_point_c: f32) -> Option<f32> {
+    if temp_c < HUMIDEX_MIN_C {
+        return None;
+    }
+    let vapour_pressure = 6.11 * (5417.753 * (1.0 / 273.16 - 1.0 / (273.15 + dew_point_c))).exp();
+    Some(meteo::round(temp_c + 0.5555 * (vapour_pressure - 10.0)))
+}
+
+pub fn apparent_temperature(temp_c: f32, humidity: f32, wind_kph: f32) -> f32 {
+    let vapour_pressure = humidity / 100.0 * 6.105 * (17.27 * temp_c / (237.7 + temp_c)).exp();
+    meteo::round(temp_c + 0.33 * vapour_pressure - 0.70 * wind_kph * KPH_TO_MPS - 4.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apparent_temperature, heat_index, humidex, wind_chill};
+
+    fn fahrenheit(celsius: f32) -> f32 {
+        celsius * 9.0 / 5.0 + 32.0
+    }
+
+    fn celsius(fahrenheit: f32) -> f32 {
+        (fahrenheit - 32.0) * 5.0 / 9.0
+    }
+
+    // NWS heat index chart, values in °F
+    #[test]
+    fn heat_index_by_nws_chart() {
+        for (temp, humidity, expected) in [
+            (80.0, 40.0, 80.0),
+            (90.0, 60.0, 100.0),
+            (96.0, 65.0, 121.0),
+            (100.0, 40.0, 109.0),
+            (86.0, 90.0, 105.0),
+        ] {
+            let index = heat_index(celsius(temp), humidity).unwrap();
+            assert!(
+                (fahrenheit(index) - expected).abs() <= 1.0,
+                "{temp}°F {humidity}%: {}",
+                fahrenheit(index)
+            );
+        }
+        assert_eq!(heat_index(20.0, 50.0), None);
+    }
+
+    // Environment Canada wind chill chart
+    #[test]
+    fn wind_chill_by_environment_canada_chart() {
+        for (temp, wind, expected) in [
+            (0.0, 10.0, -3.0),
+            (-10.0, 20.0, -18.0),
+            (-20.0, 30.0, -33.0),
+            (-30.0, 50.0, -49.0),
+            (5.0, 40.0, -1.0),
+        ] {
+            let chill = wind_chill(temp, wind).unwrap();
+            assert_eq!(chill.round(), expected, "{temp}°C {wind} Km/h: {chill}");
+        }
+        assert_eq!(wind_chill(15.0, 30.0), None);
+        assert_eq!(wind_chill(-5.0, 3.0), None);
+    }
+
+    // Environment Canada humidex table by dew point
+    #[test]
+    fn humidex_by_environment_canada_table() {
+        for (temp, dew_point, expected) in
+            [(30.0, 15.0, 34.0), (30.0, 20.0, 38.0), (35.0, 25.0, 47.0)]
+        {
+            let index = humidex(temp, dew_point).unwrap();
+            assert_eq!(index.round(), expected, "{temp}°C {dew_point}°C: {index}");
+        }
+        assert_eq!(humidex(15.0, 10.0), None);
+    }
+
+    // Bureau of Meteorology: 30°C, 50% and calm air feels like 33°C
+    #[test]
+    fn apparent_temperature_by_steadman() {
+        assert_eq!(apparent_temperature(30.0, 50.0, 0.0).round(), 33.0);
+        assert!(apparent_temperature(0.0, 80.0, 36.0) < -5.0);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::utils::output;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/*
+Providers describe weather with their own texts and codes:
+- WeatherAPI condition codes https://www.weatherapi.com/docs/weather_conditions.json
+- OpenWeather weather ids https://openweathermap.org/weather-conditions
+- WMO 4677 codes as used by Open-Meteo https://open-meteo.com/en/docs
+- MET Norway symbol codes https://api.met.no/weatherapi/weathericon/2.0/documentation
+All of them are mapped to one `Condition`, raw provider text is kept alongside in the forecast.
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Overcast,
+    Fog,
+    Haze,
+    Drizzle,
+    Rain,
+    HeavyRain,
+    FreezingRain,
+    Sleet,
+    Snow,
+    HeavySnow,
+    Hail,
+    Thunderstorm,
+    Squall,
+    Unknown,
+}
+
+impl Condition {
+    pub const ALL: [Condition; 17] = [
+        Condition::Clear,
+        Condition::PartlyCloudy,
+        Condition::Cloudy,
+        Condition::Overcast,
+        Condition::Fog,
+        Condition::Haze,
+        Condition::Drizzle,
+        Condition::Rain,
+        Condition::HeavyRain,
+        Condition::FreezingRain,
+        Condition::Sleet,
+        Condition::Snow,
+        Condition::HeavySnow,
+        Condition::Hail,
+        Condition::Thunderstorm,
+        Condition::Squall,
+        Condition::Unknown,
+    ];
+
+    pub fn from_weatherapi_code(code: u32) -> Self {
+        match code {
+            1000 => Condition::Clear,
+            1003 => Condition::PartlyCloudy,
+            1006 => Condition::Cloudy,
+            1009 => Condition::Overcast,
+            1030 | 1135 | 1147 => Condition::Fog,
+            1063 | 1180 | 1183 | 1186 | 1189 | 1240 => Condition::Rain,
+            1192 | 1195 | 1243 | 1246 => Condition::HeavyRain,
+            1072 | 1150 | 1153 => Condition::Drizzle,
+            1168 | 1171 | 1198 | 1201 => Condition::FreezingRain,
+            1069 | 1204 | 1207 | 1249 | 1252 => Condition::Sleet,
+            1066 | 1210 | 1213 | 1216 | 1219 | 1255 => Condition::Snow,
+            1114 | 1117 | 1222 | 1225 | 1258 => Condition::HeavySnow,
+            1237 | 1261 | 1264 => Condition::Hail,
+            1087 | 1273 | 1276 | 1279 | 1282 => Condition::Thunderstorm,
+            _ => Condition::Unknown,
+        }
+    }
+
+    pub fn from_openweather_id(id: u32) -> Self {
+        match id {
+            200..=299 => Condition::Thunderstorm,
+            300..=399 => Condition::Drizzle,
+            500 | 501 | 520 | 521 | 531 => Condition::Rain,
+            502..=504 | 522 => Condition::HeavyRain,
+            511 => Condition::FreezingRain,
+            600 | 601 | 620 | 621 => Condition::Snow,
+            602 | 622 => Condition::HeavySnow,
+            611..=616 => Condition::Sleet,
+            701 | 741 => Condition::Fog,
+            711 | 721 | 731 | 751 | 761 | 762 => Condition::Haze,
+            771 | 781 => Condition::Squall,
+            800 => Condition::Clear,
+            801 | 802 => Condition::PartlyCloudy,
+            803 => Condition::Cloudy,
+            804 => Condition::Overcast,
+            _ => Condition::Unknown,
+        }
+    }
+
+    pub fn from_wmo_code(code: u32) -> Self {
+        match code {
+            0 => Condition::Clear,
+            1 | 2 => Condition::PartlyCloudy,
+            3 => Condition::Overcast,
+            45 | 48 => Condition::Fog,
+            51 | 53 | 55 => Condition::Drizzle,
+            56 | 57 | 66 | 67 => Condition::FreezingRain,
+            61 | 63 | 80 | 81 => Condition::Rain,
+            65 | 82 => Condition::HeavyRain,
+            71 | 73 | 77 | 85 => Condition::Snow,
+            75 | 86 => Condition::HeavySnow,
+            95 | 96 | 99 => Condition::Thunderstorm,
+            _ => Condition::Unknown,
+        }
+    }
+
+    // Variant suffix (`_day`, `_night`, `_polar_twilight`) is ignored.
+    pub fn from_metno_symbol(symbol: &str) -> Self {
+        match symbol.split('_').next().unwrap_or("") {
+            "clearsky" | "fair" => Condition::Clear,
+            "partlycloudy" => Condition::PartlyCloudy,
+            "cloudy" => Condition::Cloudy,
+            "fog" => Condition::Fog,
+            symbol if symbol.contains("thunder") => Condition::Thunderstorm,
+            "lightrain" | "rain" | "lightrainshowers" | "rainshowers" => Condition::Rain,
+            "heavyrain" | "heavyrainshowers" => Condition::HeavyRain,
+            symbol if symbol.contains("sleet") => Condition::Sleet,
+            "lightsnow" | "snow" | "lightsnowshowers" | "snowshowers" => Condition::Snow,
+            "heavysnow" | "heavysnowshowers" => Condition::HeavySnow,
+            _ => Condition::Unknown,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Condition::Clear => "clear",
+            Condition::PartlyCloudy => "partly cloudy",
+            Condition::Cloudy => "cloudy",
+            Condition::Overcast => "overcast",
+            Condition::Fog => "fog",
+            Condition::Haze => "haze",
+            Condition::Drizzle => "drizzle",
+            Condition::Rain => "rain",
+            Condition::HeavyRain => "heavy rain",
+            Condition::FreezingRain => "freezing rain",
+            Condition::Sleet => "sleet",
+            Condition::Snow => "snow",
+            Condition::HeavySnow => "heavy snow",
+            Condition::Hail => "hail",
+            Condition::Thunderstorm => "thunderstorm",
+            Condition::Squall => "squall",
+            Condition::Unknown => "unknown",
+        }
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Condition::Clear => "☀️",
+            Condition::PartlyCloudy => "⛅",
+            Condition::Cloudy => "☁️",
+            Condition::Overcast => "🌥️",
+            Condition::Fog | Condition::Haze => "🌫️",
+            Condition::Drizzle => "🌦️",
+            Condition::Rain | Condition::HeavyRain | Condition::FreezingRain => "🌧️",
+            Condition::Sleet | Condition::Snow | Condition::HeavySnow | Condition::Hail => "🌨️",
+            Condition::Thunderstorm => "⛈️",
+            Condition::Squall => "🌬️",
+            Condition::Unknown => "",
+        }
+    }
+
+    pub fn ascii(&self) -> &'static str {
+        match self {
+            Condition::Clear => "(O)",
+            Condition::PartlyCloudy => "(O~",
+            Condition::Cloudy | Condition::Overcast => "(~~)",
+            Condition::Fog | Condition::Haze => "===",
+            Condition::Drizzle => "(~~),",
+            Condition::Rain | Condition::FreezingRain => "(~~)/",
+            Condition::HeavyRain => "(~~)//",
+            Condition::Sleet | Condition::Hail => "(~~)*/",
+            Condition::Snow => "(~~)*",
+            Condition::HeavySnow => "(~~)**",
+            Condition::Thunderstorm => "(~~)!",
+            Condition::Squall => ">>>",
+            Condition::Unknown => "?",
+        }
+    }
+
+    /// Emoji, or the ascii picture when the output has to be plain.
+    pub fn icon(&self) -> &'static str {
+        match output::unicode() {
+            true => self.emoji(),
+            false => self.ascii(),
+        }
+    }
+
+    /// Filter by exact condition or by its group: `rain` also matches drizzle,
+    /// heavy and freezing rain, `snow` matches heavy snow, `cloudy` matches overcast.
+    pub fn matches(&self, filter: &Condition) -> bool {
+        self == filter || self.group() == Some(*filter)
+    }
+
+    fn group(&self) -> Option<Condition> {
+        match self {
+            Condition::Drizzle | Condition::HeavyRain | Condition::FreezingRain => {
+                Some(Condition::Rain)
+            }
+            Condition::HeavySnow => Some(Condition::Snow),
+            Condition::Overcast => Some(Condition::Cloudy),
+            Condition::Haze => Some(Condition::Fog),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Condition {
+    type Err = &'static str;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.trim().to_lowercase().replace(['_', '-'], " ");
+        Condition::ALL
+            .into_iter()
+            .find(|condition| condition.name() == name)
+            .ok_or("Unknown condition, please use: clear, partly cloudy, cloudy, overcast, fog, haze, drizzle, rain, heavy rain, freezing rain, sleet, snow, heavy snow, hail, thunderstorm, squall")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Condition;
+
+    #[test]
+    fn maps_provider_codes() {
+        assert_eq!(Condition::from_weatherapi_code(1063), Condition::Rain);
+        assert_eq!(Condition::from_weatherapi_code(1117), Condition::HeavySnow);
+        assert_eq!(Condition::from_openweather_id(500), Condition::Rain);
+        assert_eq!(Condition::from_openweather_id(211), Condition::Thunderstorm);
+        assert_eq!(Condition::from_openweather_id(800), Condition::Clear);
+        assert_eq!(Condition::from_wmo_code(45), Condition::Fog);
+        assert_eq!(Condition::from_wmo_code(95), Condition::Thunderstorm);
+        assert_eq!(
+            Condition::from_metno_symbol("partlycloudy_night"),
+            Condition::PartlyCloudy
+        );
+        assert_eq!(
+            Condition::from_metno_symbol("lightsleetshowersandthunder_day"),
+            Condition::Thunderstorm
+        );
+        assert_eq!(Condition::from_openweather_id(999), Condition::Unknown);
+    }
+
+    #[test]
+    fn parses_condition_names() {
+        assert_eq!("partly-cloudy".parse(), Ok(Condition::PartlyCloudy));
+        assert_eq!("Heavy_Rain".parse(), Ok(Condition::HeavyRain));
+        assert!("weather".parse::<Condition>().is_err());
+    }
+
+    #[test]
+    fn matches_condition_groups() {
+        assert!(Condition::Drizzle.matches(&Condition::Rain));
+        assert!(Condition::HeavySnow.matches(&Condition::Snow));
+        assert!(Condition::Rain.matches(&Condition::Rain));
+        assert!(!Condition::Rain.matches(&Condition::HeavyRain));
+        assert!(!Condition::Snow.matches(&Condition::Rain));
+    }
+}
@@ -0,0 +1,390 @@
+use crate::services::condition::Condition;
+use crate::services::executor;
+use crate::services::reading::Reading;
+use crate::services::weather_service::{ForecastResponseData, ForecastTarget, WeatherService};
+use crate::utils::date::Zone;
+use crate::utils::meteo;
+use crate::utils::output;
+
+/*
+Consensus forecast asks every known provider for the same address and date
+and combines their answers: numeric fields get mean, median and spread across providers,
+normalized condition is chosen by majority vote. Failed providers are kept in the breakdown.
+*/
+
+pub struct Spread {
+    pub mean: f32,
+    pub median: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Spread {
+    fn new(values: &[f32]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let middle = sorted.len() / 2;
+        let median = if sorted.len() % 2 == 0 {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        } else {
+            sorted[middle]
+        };
+
+        Some(Self {
+            mean: meteo::round(sorted.iter().sum::<f32>() / sorted.len() as f32),
+            median: meteo::round(median),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+        })
+    }
+
+    fn describe(&self, unit: &str) -> String {
+        format!(
+            "{}{unit} (median {}{unit}, range {}{unit}..{}{unit})",
+            self.mean, self.median, self.min, self.max
+        )
+    }
+}
+
+pub struct ProviderForecast {
+    pub provider: WeatherService,
+    pub forecast: Result<ForecastResponseData, &'static str>,
+}
+
+pub struct ConsensusForecast {
+    pub location: String,
+    pub date: String,
+    pub max_temp: Spread,
+    pub min_temp: Spread,
+    pub avg_temp: Option<Spread>,
+    pub maxwind_kph: Option<Spread>,
+    pub avghumidity: Option<Spread>,
+    /// Mean of the directions as vectors, spread of degrees has no meaning.
+    pub wind_degree: Option<f32>,
+    pub gust_kph: Option<Spread>,
+    pub precip_mm: Option<Spread>,
+    pub precip_chance: Option<Spread>,
+    pub snow_cm: Option<Spread>,
+    pub pressure_mb: Option<Spread>,
+    pub cloud_cover: Option<Spread>,
+    pub visibility_km: Option<Spread>,
+    pub uv_index: Option<Spread>,
+    pub dew_point_c: Option<Spread>,
+    pub condition: Condition,
+    pub condition_votes: usize,
+    pub providers: Vec<ProviderForecast>,
+}
+
+impl ConsensusForecast {
+    pub fn new(providers: Vec<ProviderForecast>) -> Result<Self, &'static str> {
+        let forecasts: Vec<&ForecastResponseData> = providers
+            .iter()
+            .filter_map(|provider| provider.forecast.as_ref().ok())
+            .collect();
+        let first = match forecasts.first() {
+            Some(first) => first,
+            None => return Err("No provider returned a forecast"),
+        };
+        let spread = |field: fn(&ForecastResponseData) -> f32| {
+            let values: Vec<f32> = forecasts.iter().map(|forecast| field(forecast)).collect();
+            Spread::new(&values).expect("at least one forecast")
+        };
+        // Providers without the value are left out instead of counted as zero.
+        let reading_spread = |field: fn(&ForecastResponseData) -> Reading| {
+            let values: Vec<f32> = forecasts
+                .iter()
+                .filter_map(|forecast| field(forecast).value)
+                .collect();
+            Spread::new(&values)
+        };
+        let (condition, condition_votes) = majority_condition(&forecasts);
+        let degrees: Vec<f32> = forecasts
+            .iter()
+            .filter_map(|forecast| forecast.wind_degree.value)
+            .collect();
+
+        Ok(Self {
+            location: first.location.clone(),
+            date: first.date.clone(),
+            max_temp: spread(|forecast| forecast.max_temp),
+            min_temp: spread(|forecast| forecast.min_temp),
+            avg_temp: reading_spread(|forecast| forecast.avg_temp),
+            maxwind_kph: reading_spread(|forecast| forecast.maxwind_kph),
+            avghumidity: reading_spread(|forecast| forecast.avghumidity),
+            wind_degree: meteo::mean_direction(&degrees),
+            gust_kph: reading_spread(|forecast| forecast.gust_kph),
+            precip_mm: reading_spread(|forecast| forecast.precip_mm),
+            precip_chance: reading_spread(|forecast| forecast.precip_chance),
+            snow_cm: reading_spread(|forecast| forecast.snow_cm),
+            pressure_mb: reading_spread(|forecast| forecast.pressure_mb),
+            cloud_cover: reading_spread(|forecast| forecast.cloud_cover),
+            visibility_km: reading_spread(|forecast| forecast.visibility_km),
+            uv_index: reading_spread(|forecast| forecast.uv_index),
+            dew_point_c: reading_spread(|forecast| forecast.dew_point_c),
+            condition,
+            condition_votes,
+            providers,
+        })
+    }
+
+    fn responded(&self) -> usize {
+        self.providers
+            .iter()
+            .filter(|provider| provider.forecast.is_ok())
+            .count()
+    }
+
+    fn render(&self) {
+        println!(
+            "== Consensus forecast for {} on date {} ({} of {} providers) ==",
+            self.location,
+            self.date,
+            self.responded(),
+            self.providers.len()
+        );
+        if self.condition != Condition::Unknown {
+            println!(
+                "{} {} ({} of {} providers)",
+                self.condition,
+                self.condition.icon(),
+                self.condition_votes,
+                self.responded()
+            );
+        }
+        if let Some(avg_temp) = &self.avg_temp {
+            println!(
+                "{}Average temperature {}",
+                output::icon("🌡️"),
+                avg_temp.describe("°C")
+            );
+        }
+        println!("    Min temperature {}", self.min_temp.describe("°C"));
+        println!("    Max temperature {}", self.max_temp.describe("°C"));
+        if let Some(avghumidity) = &self.avghumidity {
+            println!(
+                "{}Average humidity {}",
+                output::icon("💧"),
+                avghumidity.describe("")
+            );
+        }
+        if let Some(maxwind_kph) = &self.maxwind_kph {
+            println!(
+                "{}Wind max speed {}{}",
+                output::icon("💨"),
+                maxwind_kph.describe(" Km/h"),
+                match self.wind_degree {
+                    Some(degree) => format!(" from {}", meteo::compass(degree)),
+                    None => String::new(),
+                }
+            );
+        }
+        let lines = [
+            ("🌬️", "Wind gusts up to", &self.gust_kph, " Km/h"),
+            ("☔", "Precipitation", &self.precip_mm, " mm"),
+            ("☔", "Chance of precipitation", &self.precip_chance, "%"),
+            ("❄️", "Snowfall", &self.snow_cm, " cm"),
+            ("🧭", "Pressure", &self.pressure_mb, " hPa"),
+            ("☁️", "Cloud cover", &self.cloud_cover, "%"),
+            ("🌫️", "Visibility", &self.visibility_km, " km"),
+            ("☀️", "UV index", &self.uv_index, ""),
+            ("💦", "Dew point", &self.dew_point_c, "°C"),
+        ];
+        for (emoji, label, spread, unit) in lines {
+            if let Some(spread) = spread {
+                println!("{}{label} {}", output::icon(emoji), spread.describe(unit));
+            }
+        }
+        println!("-- Providers --");
+        for provider in &self.providers {
+            match &provider.forecast {
+                Ok(forecast) => println!(
+                    "{:<12} {:<6} {}°C (Min {}°C and Max {}°C), humidity {}, wind {} Km/h, precipitation {} mm, {}",
+                    provider.provider.name(),
+                    forecast.condition.ascii(),
+                    forecast.avg_temp,
+                    forecast.min_temp,
+                    forecast.max_temp,
+                    forecast.avghumidity,
+                    forecast.maxwind_kph,
+                    forecast.precip_mm,
+                    forecast.condition_text.as_deref().unwrap_or("")
+                ),
+                Err(error) => println!("{:<12} Error: {error}", provider.provider.name()),
+            }
+        }
+    }
+}
+
+pub fn consensus_forecast(address: &str, date: &str, tz: Option<Zone>) {
+    let target = match ForecastTarget::resolve(address, date, tz) {
+        Ok(target) => target,
+        Err(error) => return println!("Error: {error}"),
+    };
+    let jobs = WeatherService::ALL
+        .iter()
+        .map(|provider| (*provider, target.clone()))
+        .collect();
+    let providers = WeatherService::ALL
+        .iter()
+        .zip(executor::forecast_all(jobs))
+        .map(|(provider, forecast)| ProviderForecast {
+            provider: *provider,
+            forecast,
+        })
+        .collect::<Vec<ProviderForecast>>();
+    let errors: Vec<String> = providers
+        .iter()
+        .filter_map(|provider| match &provider.forecast {
+            Err(error) => Some(format!("{}: {error}", provider.provider.name())),
+            Ok(_) => None,
+        })
+        .collect();
+
+    match ConsensusForecast::new(providers) {
+        Ok(consensus) => consensus.render(),
+        Err(error) => {
+            println!("Error: {error}");
+            errors.iter().for_each(|error| println!("  {error}"));
+        }
+    }
+}
+
+// Ties are resolved in favour of the provider listed first.
+fn majority_condition(forecasts: &[&ForecastResponseData]) -> (Condition, usize) {
+    let mut votes: Vec<(Condition, usize)> = Vec::new();
+    for forecast in forecasts
+        .iter()
+        .filter(|forecast| forecast.condition != Condition::Unknown)
+    {
+        match votes
+            .iter_mut()
+            .find(|(voted, _)| *voted == forecast.condition)
+        {
+            Some((_, count)) => *count += 1,
+            None => votes.push((forecast.condition, 1)),
+        }
+    }
+    votes
+        .into_iter()
+        .fold((Condition::Unknown, 0), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConsensusForecast, ProviderForecast, Spread};
+    use crate::services::condition::Condition;
+    use crate::services::reading::{Provenance, Reading};
+    use crate::services::weather_service::{ForecastResponseData, WeatherService};
+
+    #[test]
+    fn spread_of_odd_number_of_values() {
+        let spread = Spread::new(&[13.0, 8.0, 9.0]).unwrap();
+        assert_eq!(spread.mean, 10.0);
+        assert_eq!(spread.median, 9.0);
+        assert_eq!(spread.min, 8.0);
+        assert_eq!(spread.max, 13.0);
+    }
+
+    #[test]
+    fn spread_of_even_number_of_values() {
+        let spread = Spread::new(&[13.0, 8.0]).unwrap();
+        assert_eq!(spread.mean, 10.5);
+        assert_eq!(spread.median, 10.5);
+    }
+
+    #[test]
+    fn consensus_skips_failed_providers_and_votes_condition() {
+        let consensus = ConsensusForecast::new(vec![
+            provider(
+                WeatherService::OpenWeather,
+                Ok(forecast(13.0, Condition::Cloudy)),
+            ),
+            provider(
+                WeatherService::WeatherApi,
+                Ok(forecast(8.0, Condition::Rain)),
+            ),
+            provider(WeatherService::MetNo, Ok(forecast(9.0, Condition::Rain))),
+            provider(WeatherService::MetNo, Err("Can't retrieve weather data")),
+        ])
+        .unwrap();
+
+        assert_eq!(consensus.responded(), 3);
+        let avg_temp = consensus.avg_temp.unwrap();
+        assert_eq!(avg_temp.min, 8.0);
+        assert_eq!(avg_temp.max, 13.0);
+        assert!(consensus.avghumidity.is_none());
+        assert_eq!(consensus.condition, Condition::Rain);
+        assert_eq!(consensus.condition_votes, 2);
+    }
+
+    #[test]
+    fn consensus_combines_optional_fields() {
+        let reading = |value: f32| Reading::new(Some(value), Provenance::Forecast);
+        let consensus = ConsensusForecast::new(vec![
+            provider(
+                WeatherService::OpenWeather,
+                Ok(ForecastResponseData {
+                    precip_mm: reading(4.0),
+                    pressure_mb: reading(1010.0),
+                    wind_degree: reading(350.0),
+                    ..forecast(10.0, Condition::Rain)
+                }),
+            ),
+            provider(
+                WeatherService::WeatherApi,
+                Ok(ForecastResponseData {
+                    precip_mm: reading(1.0),
+                    precip_chance: reading(70.0),
+                    wind_degree: reading(10.0),
+                    ..forecast(8.0, Condition::Rain)
+                }),
+            ),
+            provider(WeatherService::MetNo, Ok(forecast(9.0, Condition::Rain))),
+        ])
+        .unwrap();
+
+        let precip_mm = consensus.precip_mm.unwrap();
+        assert_eq!(precip_mm.mean, 2.5);
+        assert_eq!((precip_mm.min, precip_mm.max), (1.0, 4.0));
+        assert_eq!(consensus.precip_chance.unwrap().median, 70.0);
+        assert_eq!(consensus.pressure_mb.unwrap().mean, 1010.0);
+        assert!(consensus.snow_cm.is_none());
+        let wind_degree = consensus.wind_degree.unwrap();
+        assert!(!(1.0..359.0).contains(&wind_degree));
+    }
+
+    #[test]
+    fn consensus_without_forecasts() {
+        let consensus = ConsensusForecast::new(vec![provider(
+            WeatherService::OpenWeather,
+            Err("Can't retrieve weather data"),
+        )]);
+        assert!(consensus.is_err());
+    }
+
+    fn provider(
+        provider: WeatherService,
+        forecast: Result<ForecastResponseData, &'static str>,
+    ) -> ProviderForecast {
+        ProviderForecast { provider, forecast }
+    }
+
+    fn forecast(avg_temp: f32, condition: Condition) -> ForecastResponseData {
+        ForecastResponseData {
+            avg_temp: Reading::new(Some(avg_temp), Provenance::Forecast),
+            maxwind_kph: Reading::new(Some(10.0), Provenance::Forecast),
+            condition,
+            condition_text: Some(condition.to_string()),
+            ..ForecastResponseData::sample("Kyiv, UA", "2023-03-04", avg_temp - 2.0, avg_temp + 2.0)
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use crate::services::condition::Condition;
+use crate::services::executor;
+use crate::services::weather_service::{ForecastResponseData, ForecastTarget};
+use crate::utils::config::{self, WeatherCliConfig};
+use crate::utils::http_cache::cache_dir;
+use chrono::{Duration, Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/*
+Daemon polls forecasts of the configured addresses for the next days and keeps the last snapshot
+in the cache directory, so a restart doesn't report everything again.
+Only meaningful changes are reported: another condition, temperature or chance of precipitation
+moved more than the configured delta since it was last reported. Small drifts add up,
+because the value is compared with the last reported one, not with the previous poll.
+
+Events are printed as NDJSON or piped one by one to the hook command:
+
+[daemon]
+days = 3
+temp_delta = 2.0
+hook = "notify-send 'Weather changed'"
+
+[daemon.budget]
+openweather = 500
+
+Budget is the number of forecast requests per day for a provider, when it's spent
+the provider isn't asked until the next day. All days of an address come from one request.
+*/
+
+const DEFAULT_BUDGET: u32 = 500;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DaemonConfig {
+    pub days: i64,
+    pub temp_delta: f32,
+    /// Change of the chance of precipitation in percentage points.
+    pub precip_chance_delta: f32,
+    pub hook: Option<String>,
+    pub budget: HashMap<String, u32>,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            days: 3,
+            temp_delta: 2.0,
+            precip_chance_delta: 20.0,
+            hook: None,
+            budget: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct DaySnapshot {
+    location: String,
+    condition: Condition,
+    condition_text: Option<String>,
+    min_temp: f32,
+    max_temp: f32,
+    precip_chance: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+struct Budget {
+    day: String,
+    used: HashMap<String, u32>,
+    exhausted: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Snapshot {
+    /// Last reported forecast by `address|date`.
+    forecasts: BTreeMap<String, DaySnapshot>,
+    budget: Budget,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Change {
+    kind: &'static str,
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize)]
+struct Event<'a> {
+    time: String,
+    address: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<&'a str>,
+    #[serde(flatten)]
+    change: Change,
+}
+
+impl DaySnapshot {
+    fn new(forecast: &ForecastResponseData) -> Self {
+        Self {
+            location: forecast.location.clone(),
+            condition: forecast.condition,
+            condition_text: forecast.condition_text.clone(),
+            min_temp: forecast.min_temp,
+            max_temp: forecast.max_temp,
+            precip_chance: forecast.precip_chance.value,
+        }
+    }
+
+    fn condition_name(&self) -> String {
+        match &self.condition_text {
+            Some(text) => text.clone(),
+            None => self.condition.to_string(),
+        }
+    }
+
+    /// Changes worth reporting, reported values become the new baseline.
+    fn update(&mut self, current: &DaySnapshot, config: &DaemonConfig) -> Vec<Change> {
+        let mut changes = Vec::new();
+        if self.condition != current.condition || self.condition_text != current.condition_text {
+            changes.push(Change {
+                kind: "condition",
+                from: self.condition_name(),
+                to: current.condition_name(),
+            });
+            self.condition = current.condition;
+            self.condition_text = current.condition_text.clone();
+        }
+        for (kind, baseline, value) in [
+            ("min_temp", &mut self.min_temp, current.min_temp),
+            ("max_temp", &mut self.max_temp, current.max_temp),
+        ] {
+            if (value - *baseline).abs() > config.temp_delta {
+                changes.push(Change {
+                    kind,
+                    from: baseline.to_string(),
+                    to: value.to_string(),
+                });
+                *baseline = value;
+            }
+        }
+        match (self.precip_chance, current.precip_chance) {
+            (Some(baseline), Some(value))
+                if (value - baseline).abs() >= config.precip_chance_delta =>
+            {
+                changes.push(Change {
+                    kind: "precip_chance",
+                    from: baseline.to_string(),
+                    to: value.to_string(),
+                });
+                self.precip_chance = Some(value);
+            }
+            (None, value) => self.precip_chance = value,
+            _ => {}
+        }
+        self.location = current.location.clone();
+        changes
+    }
+}
+
+impl Budget {
+    /// Takes one request from the provider's budget of the day.
+    fn spend(&mut self, provider: &str, limit: u32, day: &str) -> bool {
+        if self.day != day {
+            *self = Budget {
+                day: day.to_string(),
+                ..Budget::default()
+            };
+        }
+        let used = self.used.entry(provider.to_string()).or_insert(0);
+        if *used >= limit {
+            return false;
+        }
+        *used += 1;
+        true
+    }
+
+    /// True only the first time the provider runs out during the day.
+    fn report_exhausted(&mut self, provider: &str) -> bool {
+        if self.exhausted.iter().any(|name| name == provider) {
+            return false;
+        }
+        self.exhausted.push(provider.to_string());
+        true
+    }
+}
+
+pub fn weather_daemon(interval: std::time::Duration) {
+    loop {
+        let config = config::load();
+        if config.addresses.is_empty() {
+            eprintln!("Error: Please add addresses to the config to poll them");
+        } else if config.daemon.days < 1 {
+            eprintln!("Error: Please set daemon.days to at least one day");
+        } else {
+            let mut snapshot = load_snapshot();
+            poll(&config, &mut snapshot);
+            save_snapshot(&snapshot);
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn poll(config: &WeatherCliConfig, snapshot: &mut Snapshot) {
+    let provider = config::get_provider();
+    let limit = config
+        .daemon
+        .budget
+        .get(provider.name())
+        .copied()
+        .unwrap_or(DEFAULT_BUDGET);
+    let today = Local::now().date_naive();
+    let day = today.format("%Y-%m-%d").to_string();
+
+    // Budget is spent before the requests, so all of them are sent together.
+    let last = format!("+{}d", config.daemon.days - 1);
+    let mut jobs = Vec::new();
+    let mut addresses = Vec::new();
+    for address in &config.addresses {
+        let target = match ForecastTarget::resolve(address, &last, None) {
+            Ok(target) => target,
+            Err(error) => {
+                eprintln!("Error: {address}: {error}");
+                continue;
+            }
+        };
+        if !snapshot.budget.spend(provider.name(), limit, &day) {
+            if snapshot.budget.report_exhausted(provider.name()) {
+                emit(
+                    &config.daemon,
+                    Event {
+                        time: now(),
+                        address,
+                        location: None,
+                        date: None,
+                        change: Change {
+                            kind: "budget_exhausted",
+                            from: provider.name().to_string(),
+                            to: limit.to_string(),
+                        },
+                    },
+                );
+            }
+            break;
+        }
+        jobs.push((provider, target));
+        addresses.push(address);
+    }
+
+    for (address, days) in addresses.into_iter().zip(executor::forecast_days_all(jobs)) {
+        let days = match days {
+            Ok(days) => days,
+            Err(error) => {
+                eprintln!("Error: {address}: {error}");
+                continue;
+            }
+        };
+        for forecast in &days {
+            let current = DaySnapshot::new(forecast);
+            let key = format!("{address}|{}", forecast.date);
+            match snapshot.forecasts.get_mut(&key) {
+                Some(baseline) => {
+                    for change in baseline.update(&current, &config.daemon) {
+                        emit(
+                            &config.daemon,
+                            Event {
+                                time: now(),
+                                address,
+                                location: Some(&current.location),
+                                date: Some(&forecast.date),
+                                change,
+                            },
+                        );
+                    }
+                }
+                None => {
+                    snapshot.forecasts.insert(key, current);
+                }
+            }
+        }
+    }
+
+    // Past days are never polled again.
+    let yesterday = (today - Duration::days(1)).format("%Y-%m-%d").to_string();
+    snapshot
+        .forecasts
+        .retain(|key, _| key.rsplit('|').next().unwrap_or("") >= yesterday.as_str());
+}
+
+fn emit(config: &DaemonConfig, event: Event) {
+    let line = match serde_json::to_string(&event) {
+        Ok(line) => line,
+        Err(_) => return eprintln!("Error: Can't serialize event"),
+    };
+    match &config.hook {
+        Some(hook) => {
+            if let Err(error) = run_hook(hook, &line) {
+                eprintln!("Error: {error}");
+            }
+        }
+        None => println!("{line}"),
+    }
+}
+
+// Event is given to the hook on stdin.
+fn run_hook(hook: &str, line: &str) -> Result<(), &'static str> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|_| "Can't run daemon hook")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{line}").map_err(|_| "Can't write event to daemon hook")?;
+    }
+    match child.wait() {
+        Ok(status) if status.success() => Ok(()),
+        _ => Err("Daemon hook failed"),
+    }
+}
+
+fn now() -> String {
+    Local::now().to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+fn snapshot_path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("daemon").join("snapshot.json"))
+}
+
+fn load_snapshot() -> Snapshot {
+    snapshot_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_snapshot(snapshot: &Snapshot) {
+    let path = match snapshot_path() {
+        Some(path) => path,
+        None => return,
+    };
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    match serde_json::to_string(snapshot) {
+        Ok(content) => {
+            if fs::write(path, content).is_err() {
+                eprintln!("Error: Can't save daemon snapshot");
+            }
+        }
+        Err(_) => eprintln!("Error: Can't serialize daemon snapshot"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Budget, DaemonConfig, DaySnapshot};
+    use crate::services::condition::Condition;
+
+    fn day(condition: Condition, max_temp: f32, precip_chance: Option<f32>) -> DaySnapshot {
+        DaySnapshot {
+            location: "Kyiv, UA".to_string(),
+            condition,
+            condition_text: None,
+            min_temp: -2.0,
+            max_temp,
+            precip_chance,
+        }
+    }
+
+    #[test]
+    fn reports_meaningful_changes_only() {
+        let config = DaemonConfig::default();
+        let mut baseline = day(Condition::Cloudy, 5.0, Some(10.0));
+
+        let changes = baseline.update(&day(Condition::Cloudy, 6.5, Some(25.0)), &config);
+        assert!(changes.is_empty());
+
+        let changes = baseline.update(&day(Condition::Rain, 7.5, Some(40.0)), &config);
+        let kinds: Vec<&str> = changes.iter().map(|change| change.kind).collect();
+        assert_eq!(kinds, vec!["condition", "max_temp", "precip_chance"]);
+        assert_eq!(changes[0].from, "cloudy");
+        assert_eq!(changes[0].to, "rain");
+        assert_eq!(changes[1].from, "5");
+        assert_eq!(baseline, day(Condition::Rain, 7.5, Some(40.0)));
+    }
+
+    #[test]
+    fn budget_is_spent_per_day() {
+        let mut budget = Budget::default();
+        assert!(budget.spend("metno", 2, "2025-01-05"));
+        assert!(budget.spend("metno", 2, "2025-01-05"));
+        assert!(!budget.spend("metno", 2, "2025-01-05"));
+        assert!(budget.spend("weatherapi", 2, "2025-01-05"));
+        assert!(budget.report_exhausted("metno"));
+        assert!(!budget.report_exhausted("metno"));
+
+        assert!(budget.spend("metno", 2, "2025-01-06"));
+        assert!(budget.report_exhausted("metno"));
+    }
+}
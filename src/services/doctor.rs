@@ -0,0 +1,413 @@
+use crate::services::weather_service::WeatherService;
+use crate::services::{geocoding, open_meteo_strategy};
+use crate::utils::config::{self, Scope};
+use crate::utils::http;
+use crate::utils::http_cache::cache_dir;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::header::DATE;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+
+/*
+Checks what forecasts depend on and prints one line per check, to be pasted into a bug report:
+
+PASS  config     /home/me/.config/weather-cli/default-config.toml (version 2)
+PASS  api key    openweather: set at build time
+FAIL  dns        api.weatherapi.com: can't resolve
+PASS  tls        https://api.met.no: HTTP 200
+WARN  clock      3 min ahead of the providers
+PASS  writable   /home/me/.cache/weather-cli
+
+TLS is checked with a HEAD request through the configured proxy and certificates, any HTTP status
+means the connection works. Clock is compared with the `Date` of the answers, a wrong clock
+makes `today` and `tomorrow` another day. Exit code is 1 when any check fails.
+*/
+
+const EXIT_FAILED: i32 = 1;
+const CLOCK_WARN: i64 = 60;
+const CLOCK_FAIL: i64 = 300;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+#[derive(Debug, PartialEq)]
+struct Check {
+    status: Status,
+    name: &'static str,
+    detail: String,
+}
+
+impl Check {
+    fn new(status: Status, name: &'static str, detail: impl Into<String>) -> Self {
+        Check {
+            status,
+            name,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Network operations of the checks, a stub replaces them in tests.
+trait Network {
+    /// Number of addresses of the host.
+    fn resolve(&self, host: &str) -> Result<usize, &'static str>;
+    /// Status and `Date` of the answer to a HEAD request.
+    fn head(&self, url: &str) -> Result<(u16, Option<DateTime<Utc>>), &'static str>;
+}
+
+struct SystemNetwork;
+
+impl Network for SystemNetwork {
+    fn resolve(&self, host: &str) -> Result<usize, &'static str> {
+        match (host, 443).to_socket_addrs() {
+            Ok(addresses) => Ok(addresses.count()),
+            Err(_) => Err("can't resolve"),
+        }
+    }
+
+    fn head(&self, url: &str) -> Result<(u16, Option<DateTime<Utc>>), &'static str> {
+        let response = http::blocking_client()?
+            .head(url)
+            .send()
+            .map_err(|error| connection_error(&error))?;
+        let date = response
+            .headers()
+            .get(DATE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.with_timezone(&Utc));
+        Ok((response.status().as_u16(), date))
+    }
+}
+
+fn connection_error(error: &reqwest::Error) -> &'static str {
+    let mut source: Option<&dyn Error> = Some(error);
+    while let Some(error) = source {
+        if error.to_string().to_lowercase().contains("certificate") {
+            return "certificate isn't trusted, set http.ca_bundle";
+        }
+        source = error.source();
+    }
+    if let Some(error) = http::proxy_error(error) {
+        return error;
+    }
+    match error.is_timeout() {
+        true => "no answer in time",
+        false => "can't connect",
+    }
+}
+
+pub fn weather_doctor() -> i32 {
+    println!(
+        "weather-cli {} ({}/{})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let mut checks = config_checks();
+    let provider = config::get_provider();
+    checks.extend(
+        WeatherService::ALL
+            .iter()
+            .map(|service| key_check(*service, service.api_key(), *service == provider)),
+    );
+    let proxy = http::settings().proxy.is_some();
+    checks.extend(network_checks(&SystemNetwork, &urls(), proxy, Utc::now()));
+    checks.extend(writable_checks());
+
+    for check in &checks {
+        let status = match check.status {
+            Status::Pass => "PASS",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+            Status::Skip => "SKIP",
+        };
+        println!("{status:<5} {:<10} {}", check.name, check.detail);
+    }
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == Status::Fail)
+        .count();
+    match failed {
+        0 => 0,
+        failed => {
+            println!("{failed} check(s) failed");
+            EXIT_FAILED
+        }
+    }
+}
+
+fn config_checks() -> Vec<Check> {
+    let mut checks = Vec::new();
+    for scope in Scope::ALL {
+        match config::file_status(scope) {
+            Some((path, Ok(fixes))) if fixes.is_empty() => checks.push(Check::new(
+                Status::Pass,
+                "config",
+                format!("{} (version {})", path.display(), config::CONFIG_VERSION),
+            )),
+            Some((path, Ok(fixes))) => checks.push(Check::new(
+                Status::Warn,
+                "config",
+                format!(
+                    "{}: {}, run `weather config doctor --fix`",
+                    path.display(),
+                    fixes.join("; ")
+                ),
+            )),
+            Some((_, Err(error))) => checks.push(Check::new(Status::Fail, "config", error)),
+            None if scope == Scope::User => {
+                let detail = match config::path(scope) {
+                    Some(path) => format!("{} doesn't exist, defaults are used", path.display()),
+                    None => "no config directory, defaults are used".to_string(),
+                };
+                checks.push(Check::new(Status::Skip, "config", detail));
+            }
+            None => {}
+        }
+    }
+    checks.push(match config::try_load() {
+        Ok(config) => Check::new(
+            Status::Pass,
+            "config",
+            format!("settings are valid, provider {}", config.provider),
+        ),
+        Err(error) => Check::new(Status::Fail, "config", error),
+    });
+    checks
+}
+
+fn key_check(provider: WeatherService, key: Option<&str>, configured: bool) -> Check {
+    let name = provider.name();
+    match key {
+        None => Check::new(Status::Pass, "api key", format!("{name}: not needed")),
+        Some(key) if !key.trim().is_empty() => Check::new(
+            Status::Pass,
+            "api key",
+            format!("{name}: set at build time"),
+        ),
+        Some(_) if configured => Check::new(
+            Status::Fail,
+            "api key",
+            format!("{name}: empty, but it's the configured provider"),
+        ),
+        Some(_) => Check::new(
+            Status::Warn,
+            "api key",
+            format!("{name}: empty, the provider can't be used"),
+        ),
+    }
+}
+
+/// Endpoints of all providers, geocoding and history.
+fn urls() -> Vec<&'static str> {
+    let mut urls: Vec<&'static str> = WeatherService::ALL
+        .iter()
+        .filter_map(|provider| provider.base_url())
+        .collect();
+    urls.push(open_meteo_strategy::ARCHIVE_URL);
+    urls.push(geocoding::URL);
+    urls
+}
+
+fn network_checks(
+    network: &impl Network,
+    urls: &[&str],
+    proxy: bool,
+    now: DateTime<Utc>,
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let mut skews = Vec::new();
+    let mut hosts = BTreeSet::new();
+    for url in urls {
+        let host = match url::Url::parse(url) {
+            Ok(parsed) => match parsed.host_str() {
+                Some(host) => host.to_string(),
+                None => continue,
+            },
+            Err(_) => continue,
+        };
+        if !hosts.insert(host.clone()) {
+            continue;
+        }
+        checks.push(match network.resolve(&host) {
+            Ok(count) => Check::new(Status::Pass, "dns", format!("{host}: {count} address(es)")),
+            // The proxy resolves hosts on its own.
+            Err(error) if proxy => Check::new(
+                Status::Warn,
+                "dns",
+                format!("{host}: {error}, requests go through the proxy"),
+            ),
+            Err(error) => Check::new(Status::Fail, "dns", format!("{host}: {error}")),
+        });
+        let origin = format!("https://{host}");
+        checks.push(match network.head(url) {
+            Ok((status, date)) => {
+                skews.extend(date.map(|date| now - date));
+                Check::new(Status::Pass, "tls", format!("{origin}: HTTP {status}"))
+            }
+            Err(error) => Check::new(Status::Fail, "tls", format!("{origin}: {error}")),
+        });
+    }
+    checks.push(clock_check(skews));
+    checks
+}
+
+// Median of the answers, a single slow or wrong server doesn't count.
+fn clock_check(mut skews: Vec<Duration>) -> Check {
+    if skews.is_empty() {
+        return Check::new(Status::Skip, "clock", "no provider answered with a date");
+    }
+    skews.sort();
+    let skew = skews[skews.len() / 2];
+    let seconds = skew.num_seconds();
+    let direction = match seconds < 0 {
+        true => "behind",
+        false => "ahead of",
+    };
+    let detail = match seconds.abs() {
+        seconds if seconds < 120 => format!("{seconds} s {direction} the providers"),
+        seconds => format!("{} min {direction} the providers", seconds / 60),
+    };
+    match seconds.abs() {
+        seconds if seconds <= CLOCK_WARN => Check::new(Status::Pass, "clock", detail),
+        seconds if seconds <= CLOCK_FAIL => Check::new(Status::Warn, "clock", detail),
+        _ => Check::new(
+            Status::Fail,
+            "clock",
+            format!("{detail}, dates like today may be resolved wrong"),
+        ),
+    }
+}
+
+fn writable_checks() -> Vec<Check> {
+    let directories = [
+        config::path(Scope::User).and_then(|path| path.parent().map(Path::to_path_buf)),
+        cache_dir(),
+    ];
+    directories
+        .into_iter()
+        .flatten()
+        .map(|dir| match writable(&dir) {
+            Ok(()) => Check::new(Status::Pass, "writable", dir.display().to_string()),
+            Err(error) => Check::new(
+                Status::Fail,
+                "writable",
+                format!("{}: {error}", dir.display()),
+            ),
+        })
+        .collect()
+}
+
+fn writable(dir: &Path) -> Result<(), String> {
+    let probe = dir.join(".doctor");
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b""))
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clock_check, key_check, network_checks, Check, Network, Status};
+    use crate::services::weather_service::WeatherService;
+    use chrono::{DateTime, Duration, Utc};
+
+    struct StubNetwork {
+        now: DateTime<Utc>,
+    }
+
+    impl Network for StubNetwork {
+        fn resolve(&self, host: &str) -> Result<usize, &'static str> {
+            match host {
+                "api.met.no" | "api.open-meteo.com" => Ok(2),
+                _ => Err("can't resolve"),
+            }
+        }
+
+        fn head(&self, url: &str) -> Result<(u16, Option<DateTime<Utc>>), &'static str> {
+            match url {
+                "https://api.met.no/forecast" => Ok((200, Some(self.now - Duration::minutes(10)))),
+                "https://api.open-meteo.com/v1/forecast" => Ok((405, None)),
+                _ => Err("can't connect"),
+            }
+        }
+    }
+
+    #[test]
+    fn network_of_every_host_once() {
+        let now = Utc::now();
+        let urls = [
+            "https://api.met.no/forecast",
+            "https://api.open-meteo.com/v1/forecast",
+            "https://api.open-meteo.com/v1/other",
+            "https://api.weatherapi.com/v1/",
+        ];
+        let checks = network_checks(&StubNetwork { now }, &urls, false, now);
+        let details: Vec<(Status, &str)> = checks
+            .iter()
+            .map(|check| (check.status, check.detail.as_str()))
+            .collect();
+        assert_eq!(
+            details,
+            vec![
+                (Status::Pass, "api.met.no: 2 address(es)"),
+                (Status::Pass, "https://api.met.no: HTTP 200"),
+                (Status::Pass, "api.open-meteo.com: 2 address(es)"),
+                (Status::Pass, "https://api.open-meteo.com: HTTP 405"),
+                (Status::Fail, "api.weatherapi.com: can't resolve"),
+                (Status::Fail, "https://api.weatherapi.com: can't connect"),
+                (
+                    Status::Fail,
+                    "10 min ahead of the providers, dates like today may be resolved wrong"
+                ),
+            ]
+        );
+
+        let checks = network_checks(&StubNetwork { now }, &urls[3..], true, now);
+        assert_eq!(checks[0].status, Status::Warn);
+        assert_eq!(checks[2].status, Status::Skip);
+    }
+
+    #[test]
+    fn clock_skew() {
+        let check = |seconds: Vec<i64>| {
+            clock_check(seconds.into_iter().map(Duration::seconds).collect()).status
+        };
+        assert_eq!(check(vec![-5, 3, 400]), Status::Pass);
+        assert_eq!(check(vec![-90]), Status::Warn);
+        assert_eq!(check(vec![-600, -500]), Status::Fail);
+        assert_eq!(
+            clock_check(vec![Duration::seconds(-90)]).detail,
+            "90 s behind the providers"
+        );
+    }
+
+    #[test]
+    fn api_keys() {
+        assert_eq!(
+            key_check(WeatherService::MetNo, None, true),
+            Check::new(Status::Pass, "api key", "metno: not needed")
+        );
+        assert_eq!(
+            key_check(WeatherService::OpenWeather, Some("abc"), true).status,
+            Status::Pass
+        );
+        assert_eq!(
+            key_check(WeatherService::WeatherApi, Some(""), false).status,
+            Status::Warn
+        );
+        assert_eq!(
+            key_check(WeatherService::WeatherApi, Some(" "), true).status,
+            Status::Fail
+        );
+    }
+}
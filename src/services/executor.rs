@@ -0,0 +1,312 @@
+use crate::services::quota;
+use crate::services::weather_service::{
+    ForecastResponseData, ForecastTarget, Source, WeatherService,
+};
+use crate::utils::http::{self, HttpError};
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/*
+Forecasts are fetched on one shared runtime, so operations with several locations, days or
+providers send their requests in parallel, but not more than `MAX_REQUESTS` at once
+and not more than the provider's own limit, which is shared by all callers of the process.
+*/
+
+const MAX_REQUESTS: usize = 8;
+
+/// What went wrong with a forecast, so errors can be counted without reading their messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Request can't be built: unknown location, date the provider doesn't have.
+    Request,
+    Network,
+    Timeout,
+    /// Quota of the provider in this app is used up, nothing was sent.
+    Quota,
+    Throttled,
+    /// Provider answered with an error status.
+    Rejected,
+    /// Response has no forecast for the date or can't be parsed.
+    Response,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ForecastError {
+    pub kind: ErrorKind,
+    pub message: &'static str,
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Request => "request",
+            ErrorKind::Network => "network",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Quota => "quota",
+            ErrorKind::Throttled => "throttled",
+            ErrorKind::Rejected => "rejected",
+            ErrorKind::Response => "response",
+        }
+    }
+
+    /// Error of this kind with the message.
+    pub fn error(self, message: &'static str) -> ForecastError {
+        ForecastError {
+            kind: self,
+            message,
+        }
+    }
+}
+
+impl From<&HttpError> for ErrorKind {
+    fn from(error: &HttpError) -> Self {
+        match error {
+            HttpError::Failed(_) => ErrorKind::Network,
+            HttpError::Timeout => ErrorKind::Timeout,
+            HttpError::Limited(_) => ErrorKind::Quota,
+            HttpError::Status(429, _) => ErrorKind::Throttled,
+            HttpError::Status(_, _) => ErrorKind::Rejected,
+        }
+    }
+}
+
+struct Limits {
+    total: Semaphore,
+    openweather: Semaphore,
+    weatherapi: Semaphore,
+    // MET Norway asks clients to keep the traffic moderate.
+    metno: Semaphore,
+    openmeteo: Semaphore,
+}
+
+impl Limits {
+    fn provider(&self, source: Source) -> &Semaphore {
+        match source {
+            Source::Forecast(WeatherService::OpenWeather) => &self.openweather,
+            Source::Forecast(WeatherService::MetNo) => &self.metno,
+            Source::OpenMeteo => &self.openmeteo,
+            _ => &self.weatherapi,
+        }
+    }
+}
+
+fn limits() -> &'static Limits {
+    static LIMITS: OnceLock<Limits> = OnceLock::new();
+    LIMITS.get_or_init(|| Limits {
+        total: Semaphore::new(MAX_REQUESTS),
+        openweather: Semaphore::new(4),
+        weatherapi: Semaphore::new(4),
+        metno: Semaphore::new(2),
+        openmeteo: Semaphore::new(4),
+    })
+}
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Can't start async runtime")
+    })
+}
+
+/// Runs the future to the end from synchronous code.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    runtime().block_on(future)
+}
+
+async fn permits(
+    source: Source,
+) -> Result<(SemaphorePermit<'static>, SemaphorePermit<'static>), ForecastError> {
+    let limits = limits();
+    match (
+        limits.provider(source).acquire().await,
+        limits.total.acquire().await,
+    ) {
+        (Ok(provider), Ok(total)) => Ok((provider, total)),
+        _ => Err(ErrorKind::Request.error("Can't create request")),
+    }
+}
+
+/// Forecast and how long the provider took to answer, without the time waiting for the limits.
+async fn limited(
+    provider: WeatherService,
+    target: &ForecastTarget,
+) -> (Result<ForecastResponseData, ForecastError>, Duration) {
+    let permits = match permits(provider.into()).await {
+        Ok(permits) => permits,
+        Err(error) => return (Err(error), Duration::ZERO),
+    };
+    let started = Instant::now();
+    let forecast = provider.forecast_async(target).await;
+    drop(permits);
+    (forecast, started.elapsed())
+}
+
+/// One forecast within the limits.
+pub fn forecast(
+    provider: WeatherService,
+    target: &ForecastTarget,
+) -> Result<ForecastResponseData, &'static str> {
+    block_on(limited(provider, target))
+        .0
+        .map_err(|error| error.message)
+}
+
+async fn limited_days(
+    provider: WeatherService,
+    target: &ForecastTarget,
+) -> Result<Vec<ForecastResponseData>, &'static str> {
+    let _permits = permits(provider.into())
+        .await
+        .map_err(|error| error.message)?;
+    provider
+        .forecast_days_async(target)
+        .await
+        .map_err(|error| error.message)
+}
+
+/// Days up to the target's date from one request within the limits.
+pub fn forecast_days(
+    provider: WeatherService,
+    target: &ForecastTarget,
+) -> Result<Vec<ForecastResponseData>, &'static str> {
+    block_on(limited_days(provider, target))
+}
+
+/// Days up to the date of every job in parallel, one request per job,
+/// results are in the order of the jobs.
+pub fn forecast_days_all(
+    jobs: Vec<(WeatherService, ForecastTarget)>,
+) -> Vec<Result<Vec<ForecastResponseData>, &'static str>> {
+    block_on(async {
+        let handles: Vec<_> = jobs
+            .into_iter()
+            .map(|(provider, target)| {
+                tokio::spawn(async move { limited_days(provider, &target).await })
+            })
+            .collect();
+        let mut results = Vec::with_capacity(handles.len());
+        for handle in handles {
+            results.push(match handle.await {
+                Ok(result) => result,
+                Err(_) => Err("Can't retrieve weather data"),
+            });
+        }
+        results
+    })
+}
+
+/// Forecasts for all jobs in parallel, results are in the order of the jobs.
+pub fn forecast_all(
+    jobs: Vec<(WeatherService, ForecastTarget)>,
+) -> Vec<Result<ForecastResponseData, &'static str>> {
+    forecast_all_timed(jobs)
+        .into_iter()
+        .map(|(forecast, _)| forecast.map_err(|error| error.message))
+        .collect()
+}
+
+/// Forecasts for all jobs in parallel with the time each one took and the kind of its error.
+pub fn forecast_all_timed(
+    jobs: Vec<(WeatherService, ForecastTarget)>,
+) -> Vec<(Result<ForecastResponseData, ForecastError>, Duration)> {
+    block_on(async {
+        let handles: Vec<_> = jobs
+            .into_iter()
+            .map(|(provider, target)| tokio::spawn(async move { limited(provider, &target).await }))
+            .collect();
+        let mut results = Vec::with_capacity(handles.len());
+        for handle in handles {
+            results.push(match handle.await {
+                Ok(result) => result,
+                Err(_) => (
+                    Err(ErrorKind::Network.error("Can't retrieve weather data")),
+                    Duration::ZERO,
+                ),
+            });
+        }
+        results
+    })
+}
+
+/// Bodies of all urls of the source in parallel within the limits, results are in the order of the urls.
+pub fn get_all(source: Source, urls: Vec<String>) -> Vec<Result<String, HttpError>> {
+    block_on(async {
+        let handles: Vec<_> = urls
+            .into_iter()
+            .map(|url| {
+                tokio::spawn(async move {
+                    let _permits = permits(source)
+                        .await
+                        .map_err(|error| HttpError::Failed(error.message))?;
+                    http::get_text(&url, || quota::take(source)).await
+                })
+            })
+            .collect();
+        let mut results = Vec::with_capacity(handles.len());
+        for handle in handles {
+            results.push(match handle.await {
+                Ok(result) => result,
+                Err(_) => Err(HttpError::Failed("Can't retrieve weather data")),
+            });
+        }
+        results
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{block_on, limits, runtime, ErrorKind};
+    use crate::services::weather_service::WeatherService;
+    use crate::utils::http::HttpError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn provider_limit_bounds_parallel_requests() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let highest = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..6)
+            .map(|_| {
+                let running = Arc::clone(&running);
+                let highest = Arc::clone(&highest);
+                runtime().spawn(async move {
+                    let _permit = limits()
+                        .provider(WeatherService::MetNo.into())
+                        .acquire()
+                        .await
+                        .unwrap();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    highest.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        block_on(async {
+            for handle in handles {
+                handle.await.unwrap();
+            }
+        });
+        assert_eq!(highest.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn http_errors_have_kinds() {
+        let kind = |error: HttpError| ErrorKind::from(&error).name();
+        assert_eq!(kind(HttpError::Timeout), "timeout");
+        assert_eq!(kind(HttpError::Limited("Daily request limit")), "quota");
+        assert_eq!(
+            kind(HttpError::Failed("Can't retrieve weather data")),
+            "network"
+        );
+        assert_eq!(kind(HttpError::Status(429, String::new())), "throttled");
+        assert_eq!(kind(HttpError::Status(401, String::new())), "rejected");
+    }
+}
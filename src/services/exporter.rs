@@ -0,0 +1,370 @@
+use crate::services::executor::{self, ForecastError};
+use crate::services::weather_service::{ForecastResponseData, ForecastTarget};
+use crate::utils::config;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Response, Server};
+
+/*
+Prometheus exporter, today's forecast of every address from the config is polled in the background
+and `/metrics` shows the last values:
+
+weather_forecast_max_temp_celsius{address="Kyiv, UA",location="Kyiv, UA",provider="metno"} 12.5
+weather_forecast_min_temp_celsius, weather_forecast_avg_temp_celsius,
+weather_forecast_humidity_percent, weather_forecast_max_wind_kph
+weather_forecast_updated_timestamp_seconds
+
+Values a provider doesn't have are left out. Provider health:
+
+weather_provider_requests_total{provider="metno"}
+weather_provider_errors_total{provider="metno",kind="network"}
+weather_provider_request_duration_seconds{provider="metno"} histogram of the whole forecast call
+*/
+
+const BUCKETS: [f64; 9] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Metric name, help and value of one forecast, `None` when the provider doesn't have it.
+type Gauge = (
+    &'static str,
+    &'static str,
+    fn(&ForecastGauges) -> Option<f64>,
+);
+
+struct ForecastGauges {
+    location: String,
+    provider: &'static str,
+    min_temp: f32,
+    max_temp: f32,
+    avg_temp: Option<f32>,
+    humidity: Option<f32>,
+    max_wind: Option<f32>,
+    updated: i64,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Observations in each bucket of `BUCKETS`, not cumulative.
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+#[derive(Default)]
+struct ProviderStats {
+    requests: u64,
+    errors: BTreeMap<&'static str, u64>,
+    latency: Histogram,
+}
+
+#[derive(Default)]
+struct Metrics {
+    forecasts: BTreeMap<String, ForecastGauges>,
+    providers: BTreeMap<&'static str, ProviderStats>,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(index) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[index] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+impl Metrics {
+    fn record(
+        &mut self,
+        provider: &'static str,
+        address: &str,
+        result: Result<&ForecastResponseData, ForecastError>,
+        elapsed: Duration,
+    ) {
+        let stats = self.providers.entry(provider).or_default();
+        stats.requests += 1;
+        stats.latency.observe(elapsed.as_secs_f64());
+        match result {
+            Ok(forecast) => {
+                self.forecasts.insert(
+                    address.to_string(),
+                    ForecastGauges {
+                        location: forecast.location.clone(),
+                        provider,
+                        min_temp: forecast.min_temp,
+                        max_temp: forecast.max_temp,
+                        avg_temp: forecast.avg_temp.value,
+                        humidity: forecast.avghumidity.value,
+                        max_wind: forecast.maxwind_kph.value,
+                        updated: Utc::now().timestamp(),
+                    },
+                );
+            }
+            // Kinds, not messages, keep the number of series small.
+            Err(error) => *stats.errors.entry(error.kind.name()).or_insert(0) += 1,
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut text = String::new();
+        let gauges: [Gauge; 6] = [
+            (
+                "weather_forecast_min_temp_celsius",
+                "Minimal temperature of today's forecast.",
+                |gauges| Some(gauges.min_temp.into()),
+            ),
+            (
+                "weather_forecast_max_temp_celsius",
+                "Maximal temperature of today's forecast.",
+                |gauges| Some(gauges.max_temp.into()),
+            ),
+            (
+                "weather_forecast_avg_temp_celsius",
+                "Average temperature of today's forecast.",
+                |gauges| gauges.avg_temp.map(f64::from),
+            ),
+            (
+                "weather_forecast_humidity_percent",
+                "Average relative humidity of today's forecast.",
+                |gauges| gauges.humidity.map(f64::from),
+            ),
+            (
+                "weather_forecast_max_wind_kph",
+                "Maximal wind speed of today's forecast in km/h.",
+                |gauges| gauges.max_wind.map(f64::from),
+            ),
+            (
+                "weather_forecast_updated_timestamp_seconds",
+                "Time of the last successful forecast.",
+                |gauges| Some(gauges.updated as f64),
+            ),
+        ];
+        for (name, help, value) in gauges {
+            let _ = writeln!(text, "# HELP {name} {help}\n# TYPE {name} gauge");
+            for (address, forecast) in &self.forecasts {
+                if let Some(value) = value(forecast) {
+                    let _ = writeln!(
+                        text,
+                        "{name}{{address=\"{}\",location=\"{}\",provider=\"{}\"}} {value}",
+                        escape(address),
+                        escape(&forecast.location),
+                        forecast.provider
+                    );
+                }
+            }
+        }
+
+        text.push_str(
+            "# HELP weather_provider_requests_total Forecast requests to the provider.\n",
+        );
+        text.push_str("# TYPE weather_provider_requests_total counter\n");
+        for (provider, stats) in &self.providers {
+            let _ = writeln!(
+                text,
+                "weather_provider_requests_total{{provider=\"{provider}\"}} {}",
+                stats.requests
+            );
+        }
+        text.push_str("# HELP weather_provider_errors_total Failed forecast requests by kind.\n");
+        text.push_str("# TYPE weather_provider_errors_total counter\n");
+        for (provider, stats) in &self.providers {
+            for (kind, count) in &stats.errors {
+                let _ = writeln!(
+                    text,
+                    "weather_provider_errors_total{{provider=\"{provider}\",kind=\"{kind}\"}} {count}"
+                );
+            }
+        }
+        let name = "weather_provider_request_duration_seconds";
+        let _ = writeln!(text, "# HELP {name} Duration of forecast requests.");
+        let _ = writeln!(text, "# TYPE {name} histogram");
+        for (provider, stats) in &self.providers {
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS.iter().zip(stats.latency.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    text,
+                    "{name}_bucket{{provider=\"{provider}\",le=\"{bound}\"}} {cumulative}"
+                );
+            }
+            let latency = &stats.latency;
+            let _ = writeln!(
+                text,
+                "{name}_bucket{{provider=\"{provider}\",le=\"+Inf\"}} {}",
+                latency.count
+            );
+            let _ = writeln!(
+                text,
+                "{name}_sum{{provider=\"{provider}\"}} {}",
+                latency.sum
+            );
+            let _ = writeln!(
+                text,
+                "{name}_count{{provider=\"{provider}\"}} {}",
+                latency.count
+            );
+        }
+        text
+    }
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+pub fn weather_exporter(bind: &str, interval: Duration) {
+    let server = match Server::http(bind) {
+        Ok(server) => server,
+        Err(_) => return println!("Error: Can't listen on {bind}"),
+    };
+    println!("Serving metrics on http://{bind}/metrics");
+    let metrics = Arc::new(Mutex::new(Metrics::default()));
+
+    let polled = Arc::clone(&metrics);
+    thread::spawn(move || loop {
+        poll(&polled);
+        thread::sleep(interval);
+    });
+
+    for request in server.incoming_requests() {
+        let response = match request.url() {
+            "/metrics" => {
+                let body = match metrics.lock() {
+                    Ok(metrics) => metrics.render(),
+                    Err(_) => String::new(),
+                };
+                let mut response = Response::from_string(body);
+                if let Ok(header) =
+                    Header::from_bytes("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+                {
+                    response.add_header(header);
+                }
+                response
+            }
+            _ => Response::from_string("Not found, please use /metrics").with_status_code(404),
+        };
+        let _ = request.respond(response);
+    }
+}
+
+fn poll(metrics: &Mutex<Metrics>) {
+    let config = config::load();
+    if config.addresses.is_empty() {
+        eprintln!("Error: Please add addresses to the config to export them");
+        return;
+    }
+    let provider = config::get_provider();
+    let mut jobs = Vec::new();
+    let mut addresses = Vec::new();
+    for address in &config.addresses {
+        match ForecastTarget::resolve(address, "today", None) {
+            Ok(target) => {
+                jobs.push((provider, target));
+                addresses.push(address);
+            }
+            Err(error) => eprintln!("Error: {address}: {error}"),
+        }
+    }
+    let forecasts = executor::forecast_all_timed(jobs);
+    let mut metrics = match metrics.lock() {
+        Ok(metrics) => metrics,
+        Err(_) => return,
+    };
+    for (address, (forecast, elapsed)) in addresses.into_iter().zip(forecasts) {
+        if let Err(error) = &forecast {
+            eprintln!("Error: {address}: {}", error.message);
+        }
+        metrics.record(
+            provider.name(),
+            address,
+            forecast.as_ref().map_err(|error| *error),
+            elapsed,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+    use crate::services::condition::Condition;
+    use crate::services::executor::ErrorKind;
+    use crate::services::reading::{Provenance, Reading};
+    use crate::services::weather_service::ForecastResponseData;
+    use std::time::Duration;
+
+    fn forecast() -> ForecastResponseData {
+        ForecastResponseData {
+            avg_temp: Reading::derived(8.0),
+            maxwind_kph: Reading::new(Some(18.0), Provenance::Forecast),
+            condition: Condition::Clear,
+            ..ForecastResponseData::sample("Kyiv, \"UA\"", "2025-05-05", 3.0, 12.5)
+        }
+    }
+
+    #[test]
+    fn renders_forecast_gauges() {
+        let mut metrics = Metrics::default();
+        let forecast = forecast();
+        metrics.record("metno", "Kyiv", Ok(&forecast), Duration::from_millis(300));
+        let text = metrics.render();
+
+        let labels = r#"{address="Kyiv",location="Kyiv, \"UA\"",provider="metno"}"#;
+        assert!(text.contains(&format!("weather_forecast_max_temp_celsius{labels} 12.5\n")));
+        assert!(text.contains(&format!("weather_forecast_avg_temp_celsius{labels} 8\n")));
+        assert!(text.contains(&format!("weather_forecast_max_wind_kph{labels} 18\n")));
+        assert!(!text.contains("weather_forecast_humidity_percent{"));
+        assert!(text.contains("# TYPE weather_forecast_humidity_percent gauge\n"));
+    }
+
+    #[test]
+    fn counts_requests_errors_and_latency() {
+        let mut metrics = Metrics::default();
+        let forecast = forecast();
+        metrics.record("metno", "Kyiv", Ok(&forecast), Duration::from_millis(300));
+        metrics.record(
+            "metno",
+            "Kyiv",
+            Err(ErrorKind::Throttled.error("Weather service is throttling requests")),
+            Duration::from_secs(2),
+        );
+        metrics.record(
+            "metno",
+            "Lviv",
+            Err(ErrorKind::Network.error("Can't retrieve weather data")),
+            Duration::from_secs(90),
+        );
+        let text = metrics.render();
+
+        assert!(text.contains("weather_provider_requests_total{provider=\"metno\"} 3\n"));
+        assert!(text
+            .contains("weather_provider_errors_total{provider=\"metno\",kind=\"throttled\"} 1\n"));
+        assert!(
+            text.contains("weather_provider_errors_total{provider=\"metno\",kind=\"network\"} 1\n")
+        );
+        let name = "weather_provider_request_duration_seconds";
+        assert!(text.contains(&format!(
+            "{name}_bucket{{provider=\"metno\",le=\"0.25\"}} 0\n"
+        )));
+        assert!(text.contains(&format!(
+            "{name}_bucket{{provider=\"metno\",le=\"0.5\"}} 1\n"
+        )));
+        assert!(text.contains(&format!(
+            "{name}_bucket{{provider=\"metno\",le=\"2.5\"}} 2\n"
+        )));
+        assert!(text.contains(&format!(
+            "{name}_bucket{{provider=\"metno\",le=\"60\"}} 2\n"
+        )));
+        assert!(text.contains(&format!(
+            "{name}_bucket{{provider=\"metno\",le=\"+Inf\"}} 3\n"
+        )));
+        assert!(text.contains(&format!("{name}_sum{{provider=\"metno\"}} 92.3\n")));
+        // Failed request keeps the last good forecast.
+        assert!(text.contains("weather_forecast_min_temp_celsius{address=\"Kyiv\""));
+    }
+}
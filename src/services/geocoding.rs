@@ -0,0 +1,123 @@
+use crate::utils::http::{self, HttpError};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/*
+Providers working with coordinates (like MET Norway) need the address resolved first.
+Open-Meteo geocoding is keyless and also returns the IANA time zone of the place:
+https://geocoding-api.open-meteo.com/v1/search?name=Kyiv&count=10&language=en&format=json
+
+The address is split by the first comma: 'Kyiv, UA' searches for 'Kyiv' and prefers
+results whose country code, country or region matches 'UA'.
+*/
+
+pub const URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+const MAX_RESULTS: &str = "10";
+
+#[derive(Clone)]
+pub struct Place {
+    pub name: String,
+    pub country: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub timezone: Tz,
+}
+
+impl Place {
+    pub fn location(&self) -> String {
+        format!("{}, {}", self.name, self.country)
+    }
+}
+
+pub fn geocode(address: &str) -> Result<Place, &'static str> {
+    let (name, hint) = split_address(address);
+    let query = build_query(name)?;
+    let response = match http::blocking_client()?.get(query).send() {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => return Err(HttpError::status_error(response.status().as_u16())),
+        Err(error) => {
+            return Err(http::proxy_error(&error).unwrap_or("Can't retrieve location data"))
+        }
+    };
+    match response.json::<GeocodingResponse>() {
+        Ok(data) => select_place(data.results, hint),
+        Err(_) => Err("Incorrect data from geocoding service: can't parse"),
+    }
+}
+
+fn split_address(address: &str) -> (&str, Option<&str>) {
+    match address.split_once(',') {
+        Some((name, hint)) if !hint.trim().is_empty() => (name.trim(), Some(hint.trim())),
+        Some((name, _)) => (name.trim(), None),
+        None => (address.trim(), None),
+    }
+}
+
+fn build_query(name: &str) -> Result<String, &'static str> {
+    match url::Url::parse(URL) {
+        Ok(mut url) => {
+            url.query_pairs_mut()
+                .append_pair("name", name)
+                .append_pair("count", MAX_RESULTS)
+                .append_pair("language", "en")
+                .append_pair("format", "json");
+            Ok(url.to_string())
+        }
+        Err(_) => Err("Can't build query"),
+    }
+}
+
+fn select_place(results: Vec<GeocodingResult>, hint: Option<&str>) -> Result<Place, &'static str> {
+    let matching = match hint {
+        Some(hint) => results
+            .iter()
+            .position(|result| result.matches(hint))
+            .unwrap_or(0),
+        None => 0,
+    };
+    let result = match results.into_iter().nth(matching) {
+        Some(result) => result,
+        None => return Err("No location found"),
+    };
+    let timezone = match result.timezone.parse::<Tz>() {
+        Ok(timezone) => timezone,
+        Err(_) => return Err("Unknown time zone of location"),
+    };
+
+    Ok(Place {
+        name: result.name,
+        country: result.country_code,
+        latitude: result.latitude,
+        longitude: result.longitude,
+        timezone,
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GeocodingResponse {
+    #[serde(default)]
+    results: Vec<GeocodingResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GeocodingResult {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    country_code: String,
+    #[serde(default)]
+    country: String,
+    #[serde(default)]
+    admin1: String,
+    #[serde(default)]
+    timezone: String,
+}
+
+impl GeocodingResult {
+    fn matches(&self, hint: &str) -> bool {
+        self.country_code.eq_ignore_ascii_case(hint)
+            || self.country.eq_ignore_ascii_case(hint)
+            || self.admin1.eq_ignore_ascii_case(hint)
+    }
+}
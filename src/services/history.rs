@@ -0,0 +1,333 @@
+use crate::services::executor;
+use crate::services::geocoding::{self, Place};
+use crate::services::open_meteo_strategy::OpenMeteoStrategy;
+use crate::services::weather_service::{ForecastResponseData, Source, WeatherService};
+use crate::services::weatherapi_strategy::WeatherApiStrategy;
+use crate::utils::date::{DateOption, Zone};
+use crate::utils::http::HttpError;
+use crate::utils::meteo;
+use crate::utils::output::{self, csv_field, optional, OutputFormat};
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+/*
+Historical weather for a range of dates. Providers limit both how far back the history goes
+and how many days one request may cover, so the range is split into pages which are fetched
+in parallel within the provider's limits.
+*/
+
+/// Day with at least this much precipitation counts as rainy (WMO "wet day").
+const RAINY_DAY_MM: f32 = 1.0;
+
+pub trait HistoryStrategy {
+    fn provider(&self) -> Source;
+
+    fn earliest_date(&self) -> NaiveDate;
+
+    fn max_days_per_request(&self) -> i64;
+
+    /// `place` is the geocoded address, if it was found.
+    fn build_history_request(
+        &self,
+        address: &str,
+        place: Option<&Place>,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<String, &'static str>;
+
+    fn build_history_response(
+        &self,
+        place: Option<&Place>,
+        body: &str,
+    ) -> Result<Vec<ForecastResponseData>, &'static str>;
+
+    /// Error from the provider's payload of a failed response.
+    fn parse_error(&self, status: u16, _body: &str) -> &'static str {
+        HttpError::status_error(status)
+    }
+}
+
+impl Source {
+    pub fn history_strategy(&self) -> Option<Box<dyn HistoryStrategy>> {
+        match self {
+            Source::Forecast(WeatherService::WeatherApi) => Some(Box::new(WeatherApiStrategy)),
+            Source::OpenMeteo => Some(Box::new(OpenMeteoStrategy)),
+            _ => None,
+        }
+    }
+}
+
+pub fn fetch_history(
+    strategy: &dyn HistoryStrategy,
+    address: &str,
+    place: Option<&Place>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<ForecastResponseData>, &'static str> {
+    if from > to {
+        return Err("Start date of the range is after its end");
+    }
+    if from < strategy.earliest_date() {
+        return Err("Sorry, selected service doesn't have history that far in the past");
+    }
+
+    let mut queries = Vec::new();
+    let mut page_from = from;
+    while page_from <= to {
+        let page_to = to.min(page_from + Duration::days(strategy.max_days_per_request() - 1));
+        queries.push(strategy.build_history_request(address, place, page_from, page_to)?);
+        page_from = page_to + Duration::days(1);
+    }
+
+    let mut days = Vec::new();
+    for body in executor::get_all(strategy.provider(), queries) {
+        let body = body
+            .map_err(|error| error.into_error(|status, body| strategy.parse_error(status, body)))?;
+        days.extend(strategy.build_history_response(place, &body)?);
+    }
+    days.sort_by(|a, b| a.date.cmp(&b.date));
+    Ok(days)
+}
+
+#[derive(Serialize)]
+pub struct HistoryStats {
+    pub days: usize,
+    pub mean_temp: Option<f32>,
+    pub min_temp: f32,
+    pub max_temp: f32,
+    pub total_precip_mm: Option<f32>,
+    pub rainy_days: Option<usize>,
+    pub hottest_day: String,
+    pub coldest_day: String,
+}
+
+impl HistoryStats {
+    pub fn new(days: &[ForecastResponseData]) -> Option<Self> {
+        let hottest = days
+            .iter()
+            .max_by(|a, b| a.max_temp.total_cmp(&b.max_temp))?;
+        let coldest = days
+            .iter()
+            .min_by(|a, b| a.min_temp.total_cmp(&b.min_temp))?;
+        let precip: Vec<f32> = days.iter().filter_map(|day| day.precip_mm.value).collect();
+        let temps: Vec<f32> = days.iter().filter_map(|day| day.avg_temp.value).collect();
+        let mean_temp = meteo::average(&temps);
+        let (total_precip_mm, rainy_days) = if precip.is_empty() {
+            (None, None)
+        } else {
+            (
+                Some(meteo::round(precip.iter().sum::<f32>())),
+                Some(precip.iter().filter(|mm| **mm >= RAINY_DAY_MM).count()),
+            )
+        };
+
+        Some(Self {
+            days: days.len(),
+            mean_temp,
+            min_temp: coldest.min_temp,
+            max_temp: hottest.max_temp,
+            total_precip_mm,
+            rainy_days,
+            hottest_day: hottest.date.clone(),
+            coldest_day: coldest.date.clone(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct HistoryReport<'a> {
+    days: &'a [ForecastResponseData],
+    stats: &'a HistoryStats,
+}
+
+pub fn weather_history(
+    address: &str,
+    from: &str,
+    to: &str,
+    provider: Source,
+    format: OutputFormat,
+) {
+    let strategy = match provider.history_strategy() {
+        Some(strategy) => strategy,
+        None => return println!("Error: Sorry, selected service doesn't provide history"),
+    };
+    let place = geocoding::geocode(address).ok();
+    let zone = place.as_ref().map(|place| Zone::from(place.timezone));
+    let days = history_range(from, to, zone)
+        .and_then(|(from, to)| fetch_history(strategy.as_ref(), address, place.as_ref(), from, to));
+
+    match days {
+        Ok(days) => match HistoryStats::new(&days) {
+            Some(stats) => render_history(&days, &stats, format),
+            None => println!("Error: No history for these dates"),
+        },
+        Err(error) => println!("Error: {error}"),
+    }
+}
+
+fn history_range(
+    from: &str,
+    to: &str,
+    zone: Option<Zone>,
+) -> Result<(NaiveDate, NaiveDate), &'static str> {
+    let from = DateOption::new(from, zone)?;
+    let to = DateOption::new(to, zone)?;
+    if to.days_from_now >= 0 {
+        return Err(
+            "History is available only for past dates, please end the range yesterday or earlier",
+        );
+    }
+    Ok((from.date, to.date))
+}
+
+fn render_history(days: &[ForecastResponseData], stats: &HistoryStats, format: OutputFormat) {
+    match format {
+        OutputFormat::Table => render_table(days, stats),
+        OutputFormat::Csv => render_csv(days),
+        OutputFormat::Json => match serde_json::to_string_pretty(&HistoryReport { days, stats }) {
+            Ok(json) => println!("{json}"),
+            Err(_) => println!("Error: Can't serialize history"),
+        },
+    }
+}
+
+fn render_table(days: &[ForecastResponseData], stats: &HistoryStats) {
+    if let Some(first) = days.first() {
+        println!(
+            "== Weather history for {} from {} to {} ==",
+            first.location,
+            first.date,
+            days[days.len() - 1].date
+        );
+    }
+    println!(
+        "{:<10} {:>7} {:>7} {:>7} {:>7} {:>6} {:>5} {:>8}  Condition",
+        "Date", "Avg °C", "Min °C", "Max °C", "Precip", "Snow", "Hum", "Wind"
+    );
+    for day in days {
+        println!(
+            "{:<10} {:>7} {:>7} {:>7} {:>7} {:>6} {:>5} {:>8}  {}",
+            day.date,
+            day.avg_temp,
+            day.min_temp,
+            day.max_temp,
+            day.precip_mm,
+            day.snow_cm,
+            day.avghumidity,
+            day.maxwind_kph,
+            day.condition_text.as_deref().unwrap_or("")
+        );
+    }
+    println!("-- {} days --", stats.days);
+    match stats.mean_temp {
+        Some(mean_temp) => println!(
+            "{}Mean temperature {}°C (Min {}°C and Max {}°C)",
+            output::icon("🌡️"),
+            mean_temp,
+            stats.min_temp,
+            stats.max_temp
+        ),
+        None => println!(
+            "{}Temperature from {}°C to {}°C",
+            output::icon("🌡️"),
+            stats.min_temp,
+            stats.max_temp
+        ),
+    }
+    if let (Some(total), Some(rainy_days)) = (stats.total_precip_mm, stats.rainy_days) {
+        println!(
+            "{}Total precipitation {total} mm, rainy days {rainy_days}",
+            output::icon("💧")
+        );
+    }
+    println!(
+        "{}Hottest day {} ({}°C)",
+        output::icon("🔥"),
+        stats.hottest_day,
+        stats.max_temp
+    );
+    println!(
+        "{}Coldest day {} ({}°C)",
+        output::icon("❄️"),
+        stats.coldest_day,
+        stats.min_temp
+    );
+}
+
+fn render_csv(days: &[ForecastResponseData]) {
+    println!("date,location,avg_temp,min_temp,max_temp,precip_mm,precip_chance,snow_cm,avghumidity,maxwind_kph,gust_kph,wind_degree,pressure_mb,cloud_cover,dew_point_c,uv_index,visibility_km,condition,condition_text");
+    for day in days {
+        println!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            day.date,
+            csv_field(&day.location),
+            optional(day.avg_temp.value),
+            day.min_temp,
+            day.max_temp,
+            optional(day.precip_mm.value),
+            optional(day.precip_chance.value),
+            optional(day.snow_cm.value),
+            optional(day.avghumidity.value),
+            optional(day.maxwind_kph.value),
+            optional(day.gust_kph.value),
+            optional(day.wind_degree.value),
+            optional(day.pressure_mb.value),
+            optional(day.cloud_cover.value),
+            optional(day.dew_point_c.value),
+            optional(day.uv_index.value),
+            optional(day.visibility_km.value),
+            day.condition.name(),
+            csv_field(day.condition_text.as_deref().unwrap_or(""))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HistoryStats;
+    use crate::services::condition::Condition;
+    use crate::services::reading::{Provenance, Reading};
+    use crate::services::weather_service::ForecastResponseData;
+
+    #[test]
+    fn aggregates_history_days() {
+        let days = vec![
+            day("2025-01-01", -3.0, 1.0, Some(0.0)),
+            day("2025-01-02", -8.0, -2.0, Some(4.5)),
+            day("2025-01-03", 0.0, 6.0, Some(1.0)),
+            day("2025-01-04", -1.0, 3.0, None),
+        ];
+        let stats = HistoryStats::new(&days).unwrap();
+
+        assert_eq!(stats.days, 4);
+        assert_eq!(stats.mean_temp, Some(-0.5));
+        assert_eq!(stats.min_temp, -8.0);
+        assert_eq!(stats.max_temp, 6.0);
+        assert_eq!(stats.total_precip_mm, Some(5.5));
+        assert_eq!(stats.rainy_days, Some(2));
+        assert_eq!(stats.hottest_day, "2025-01-03");
+        assert_eq!(stats.coldest_day, "2025-01-02");
+    }
+
+    #[test]
+    fn no_stats_without_days() {
+        assert!(HistoryStats::new(&[]).is_none());
+    }
+
+    fn day(
+        date: &str,
+        min_temp: f32,
+        max_temp: f32,
+        precip_mm: Option<f32>,
+    ) -> ForecastResponseData {
+        ForecastResponseData {
+            avg_temp: Reading::new(Some((min_temp + max_temp) / 2.0), Provenance::Measured),
+            maxwind_kph: Reading::new(Some(10.0), Provenance::Measured),
+            avghumidity: Reading::new(Some(70.0), Provenance::Measured),
+            condition: Condition::Cloudy,
+            condition_text: Some("Cloudy".to_string()),
+            precip_mm: Reading::new(precip_mm, Provenance::Measured),
+            ..ForecastResponseData::sample("Kyiv, UA", date, min_temp, max_temp)
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use crate::services::condition::Condition;
+use crate::services::geocoding::{self, Place};
+use crate::services::quota;
+use crate::services::reading::{Provenance, Reading};
+use crate::services::weather_service::{
+    ForecastQuery, ForecastResponseData, ForecastStrategy, ForecastTarget, WeatherService,
+};
+use crate::utils::date::Zone;
+use crate::utils::http::HttpError;
+use crate::utils::http_cache;
+use crate::utils::meteo;
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+
+/*
+MET Norway (yr.no) Locationforecast 2.0 is keyless, works with coordinates only:
+https://api.met.no/weatherapi/locationforecast/2.0/compact?lat=50.45&lon=30.5236
+
+Terms of service:
+- identifying User-Agent is required, anonymous requests are rejected with 403;
+- responses must be cached until `Expires` and revalidated with `If-Modified-Since`;
+- coordinates shouldn't have more than 4 decimals.
+
+Forecast is hourly timeseries for now and ~9 days in future, days are aggregated
+in the time zone of the location. Precipitation of a step is for the next hour, or the next
+6 hours when the series is already 6-hourly, so the day's total doesn't count any hour twice.
+*/
+pub struct MetNoStrategy;
+
+pub const URL: &str = "https://api.met.no/weatherapi/locationforecast/2.0/compact";
+const MAX_DAYS_FROM_NOW: i64 = 9;
+
+impl ForecastStrategy for MetNoStrategy {
+    fn provider(&self) -> WeatherService {
+        WeatherService::MetNo
+    }
+
+    fn build_request(&self, target: &ForecastTarget) -> Result<ForecastQuery, &'static str> {
+        let place = match &target.place {
+            Some(place) => place.clone(),
+            None => geocoding::geocode(&target.address)?,
+        };
+        let date_option = target.date_in(Zone::from(place.timezone))?;
+        if !(0..=MAX_DAYS_FROM_NOW).contains(&date_option.days_from_now) {
+            return Err("Sorry, selected service doesn't support date in past or date in future more than 9 days from now");
+        }
+
+        Ok(ForecastQuery {
+            url: query(&place)?,
+            date_option,
+            place: Some(place),
+        })
+    }
+
+    fn fetch(
+        &self,
+        query: &ForecastQuery,
+    ) -> impl Future<Output = Result<String, HttpError>> + Send {
+        let (url, provider) = (query.url.clone(), self.provider());
+        async move { http_cache::get(&url, || quota::take(provider.into())).await }
+    }
+
+    fn build_response(
+        &self,
+        query: &ForecastQuery,
+        body: &str,
+    ) -> Result<ForecastResponseData, &'static str> {
+        let place = match &query.place {
+            Some(place) => place,
+            None => return Err("Can't create request"),
+        };
+        match serde_json::from_str::<MetNoResponse>(body) {
+            Ok(data) => build_forecast(data, place, query.date_option.date),
+            Err(_) => Err("Incorrect data from weather service: can't parse"),
+        }
+    }
+}
+
+fn query(place: &Place) -> Result<String, &'static str> {
+    match url::Url::parse(URL) {
+        Ok(mut url) => {
+            url.query_pairs_mut()
+                .append_pair("lat", &format!("{:.4}", place.latitude))
+                .append_pair("lon", &format!("{:.4}", place.longitude));
+            Ok(url.to_string())
+        }
+        Err(_) => Err("Can't build query"),
+    }
+}
+
+fn build_forecast(
+    data: MetNoResponse,
+    place: &Place,
+    date: NaiveDate,
+) -> Result<ForecastResponseData, &'static str> {
+    let hours: Vec<&TimeStep> = data
+        .properties
+        .timeseries
+        .iter()
+        .filter(|step| local_date(&step.time, &place.timezone) == Some(date))
+        .collect();
+    if hours.is_empty() {
+        return Err("Error: No forecast for this day");
+    }
+
+    let temps: Vec<f32> = hours
+        .iter()
+        .map(|step| step.data.instant.details.air_temperature)
+        .collect();
+    let humidity: Vec<f32> = hours
+        .iter()
+        .map(|step| step.data.instant.details.relative_humidity)
+        .collect();
+    let max_wind = hours
+        .iter()
+        .map(|step| step.data.instant.details.wind_speed)
+        .fold(0.0, f32::max);
+    let symbol = dominant_symbol(&hours);
+    let periods: Vec<&Period> = hours
+        .iter()
+        .filter_map(|step| {
+            step.data
+                .next_1_hours
+                .as_ref()
+                .or(step.data.next_6_hours.as_ref())
+        })
+        .collect();
+    let precipitation = |snow_only: bool| -> f32 {
+        periods
+            .iter()
+            .filter(|period| !snow_only || period.summary.symbol_code.contains("snow"))
+            .filter_map(|period| period.details.as_ref()?.precipitation_amount)
+            .sum()
+    };
+    let has_precipitation = periods.iter().any(|period| {
+        period
+            .details
+            .as_ref()
+            .is_some_and(|details| details.precipitation_amount.is_some())
+    });
+    let instant = |field: fn(&InstantDetails) -> Option<f32>| -> Vec<f32> {
+        hours
+            .iter()
+            .filter_map(|step| field(&step.data.instant.details))
+            .collect()
+    };
+    let instant_average = |field: fn(&InstantDetails) -> Option<f32>| {
+        Reading::new(meteo::average(&instant(field)), Provenance::Derived)
+    };
+    let avg_temp = meteo::average(&temps);
+    let avghumidity = meteo::average(&humidity);
+    // Dew point is in the complete forecast only, for compact one it's computed.
+    let dew_point_c = match instant_average(|details| details.dew_point_temperature) {
+        Reading { value: None, .. } => Reading::new(
+            avg_temp
+                .zip(avghumidity)
+                .and_then(|(temp, humidity)| meteo::dew_point(temp, humidity)),
+            Provenance::Derived,
+        ),
+        dew_point => dew_point,
+    };
+
+    Ok(ForecastResponseData {
+        location: place.location(),
+        date: date.format("%Y-%m-%d").to_string(),
+        max_temp: temps.iter().cloned().fold(f32::MIN, f32::max),
+        min_temp: temps.iter().cloned().fold(f32::MAX, f32::min),
+        avg_temp: Reading::new(avg_temp, Provenance::Derived),
+        maxwind_kph: Reading::new(
+            Some(meteo::round(max_wind * meteo::MPS_TO_KPH)),
+            Provenance::Forecast,
+        ),
+        avghumidity: Reading::new(avghumidity, Provenance::Derived),
+        pressure_mb: instant_average(|details| details.air_pressure_at_sea_level),
+        uv_index: Reading::new(
+            instant(|details| details.ultraviolet_index_clear_sky)
+                .into_iter()
+                .reduce(f32::max),
+            Provenance::Forecast,
+        ),
+        visibility_km: Reading::UNAVAILABLE,
+        dew_point_c,
+        cloud_cover: instant_average(|details| details.cloud_area_fraction),
+        wind_degree: Reading::new(
+            meteo::mean_direction(&instant(|details| details.wind_from_direction)),
+            Provenance::Derived,
+        ),
+        gust_kph: Reading::new(
+            instant(|details| details.wind_speed_of_gust)
+                .into_iter()
+                .reduce(f32::max)
+                .map(|gust| meteo::round(gust * meteo::MPS_TO_KPH)),
+            Provenance::Forecast,
+        ),
+        condition: match &symbol {
+            Some(symbol) => Condition::from_metno_symbol(symbol),
+            None => Condition::Unknown,
+        },
+        condition_text: symbol.as_deref().map(describe_symbol),
+        precip_mm: Reading::new(
+            has_precipitation.then(|| meteo::round(precipitation(false))),
+            Provenance::Forecast,
+        ),
+        precip_chance: Reading::UNAVAILABLE,
+        // Snow depth from the water equivalent of the periods with snow.
+        snow_cm: Reading::new(
+            has_precipitation
+                .then(|| meteo::round(precipitation(true) * meteo::SNOW_PER_LIQUID / 10.0)),
+            Provenance::Derived,
+        ),
+        timezone: Some(Zone::from(place.timezone)),
+        normal: None,
+    })
+}
+
+fn local_date(time: &str, timezone: &Tz) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(time).ok().map(|time| {
+        time.with_timezone(&Utc)
+            .with_timezone(timezone)
+            .date_naive()
+    })
+}
+
+// Symbol codes come with a variant suffix like `clearsky_day`, only the weather part is counted.
+fn dominant_symbol(hours: &[&TimeStep]) -> Option<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for step in hours {
+        let summary = step
+            .data
+            .next_1_hours
+            .as_ref()
+            .or(step.data.next_6_hours.as_ref());
+        if let Some(summary) = summary {
+            let symbol = summary.summary.symbol_code.split('_').next().unwrap_or("");
+            *counts.entry(symbol).or_insert(0) += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by(|(a_symbol, a_count), (b_symbol, b_count)| {
+            a_count.cmp(b_count).then(b_symbol.cmp(a_symbol))
+        })
+        .map(|(symbol, _)| symbol.to_string())
+}
+
+fn describe_symbol(symbol: &str) -> String {
+    let text = match symbol {
+        "clearsky" => "Clear sky",
+        "fair" => "Fair",
+        "partlycloudy" => "Partly cloudy",
+        "cloudy" => "Cloudy",
+        "fog" => "Fog",
+        "lightrain" => "Light rain",
+        "rain" => "Rain",
+        "heavyrain" => "Heavy rain",
+        "lightrainshowers" => "Light rain showers",
+        "rainshowers" => "Rain showers",
+        "heavyrainshowers" => "Heavy rain showers",
+        "lightsleet" => "Light sleet",
+        "sleet" => "Sleet",
+        "heavysleet" => "Heavy sleet",
+        "lightsleetshowers" => "Light sleet showers",
+        "sleetshowers" => "Sleet showers",
+        "heavysleetshowers" => "Heavy sleet showers",
+        "lightsnow" => "Light snow",
+        "snow" => "Snow",
+        "heavysnow" => "Heavy snow",
+        "lightsnowshowers" => "Light snow showers",
+        "snowshowers" => "Snow showers",
+        "heavysnowshowers" => "Heavy snow showers",
+        symbol if symbol.contains("thunder") => "Thunderstorm",
+        symbol => symbol,
+    };
+    text.to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MetNoResponse {
+    properties: Properties,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Properties {
+    timeseries: Vec<TimeStep>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct TimeStep {
+    time: String,
+    data: TimeStepData,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct TimeStepData {
+    instant: Instant,
+    next_1_hours: Option<Period>,
+    next_6_hours: Option<Period>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Instant {
+    details: InstantDetails,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct InstantDetails {
+    air_temperature: f32,
+    relative_humidity: f32,
+    wind_speed: f32,
+    #[serde(default)]
+    wind_from_direction: Option<f32>,
+    #[serde(default)]
+    air_pressure_at_sea_level: Option<f32>,
+    #[serde(default)]
+    cloud_area_fraction: Option<f32>,
+    // Only in the complete forecast.
+    #[serde(default)]
+    wind_speed_of_gust: Option<f32>,
+    #[serde(default)]
+    dew_point_temperature: Option<f32>,
+    #[serde(default)]
+    ultraviolet_index_clear_sky: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Period {
+    summary: Summary,
+    #[serde(default)]
+    details: Option<PeriodDetails>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PeriodDetails {
+    #[serde(default)]
+    precipitation_amount: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Summary {
+    symbol_code: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_forecast, MetNoResponse};
+    use crate::services::condition::Condition;
+    use crate::services::geocoding::Place;
+    use crate::services::reading::{Provenance, Reading};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn aggregates_hours_of_local_day() {
+        let place = Place {
+            name: "Kyiv".to_string(),
+            country: "UA".to_string(),
+            latitude: 50.45,
+            longitude: 30.5236,
+            timezone: chrono_tz::Europe::Kyiv,
+        };
+        // 2023-03-04T22:00Z is already 2023-03-05 in Kyiv and must be skipped
+        let body = r#"{"properties": {"timeseries": [
+            {"time": "2023-03-03T22:00:00Z", "data": {"instant": {"details": {"air_temperature": 1.0, "relative_humidity": 80.0, "wind_speed": 2.0}},
+                "next_1_hours": {"summary": {"symbol_code": "lightsnow"}, "details": {"precipitation_amount": 0.4}}}},
+            {"time": "2023-03-04T09:00:00Z", "data": {"instant": {"details": {"air_temperature": 5.0, "relative_humidity": 60.0, "wind_speed": 5.0,
+                "wind_from_direction": 200.0, "air_pressure_at_sea_level": 1015.0, "cloud_area_fraction": 40.0}},
+                "next_1_hours": {"summary": {"symbol_code": "partlycloudy_day"}, "details": {"precipitation_amount": 0.0}},
+                "next_6_hours": {"summary": {"symbol_code": "rain"}, "details": {"precipitation_amount": 9.0}}}},
+            {"time": "2023-03-04T12:00:00Z", "data": {"instant": {"details": {"air_temperature": 6.0, "relative_humidity": 70.0, "wind_speed": 4.0,
+                "wind_from_direction": 220.0, "air_pressure_at_sea_level": 1013.0, "cloud_area_fraction": 60.0}},
+                "next_6_hours": {"summary": {"symbol_code": "partlycloudy_day"}, "details": {"precipitation_amount": 1.2}}}},
+            {"time": "2023-03-04T22:00:00Z", "data": {"instant": {"details": {"air_temperature": -9.0, "relative_humidity": 10.0, "wind_speed": 20.0}},
+                "next_6_hours": {"summary": {"symbol_code": "snow"}, "details": {"precipitation_amount": 5.0}}}}
+        ]}}"#;
+        let data: MetNoResponse = serde_json::from_str(body).unwrap();
+        let date = NaiveDate::from_ymd_opt(2023, 3, 4).unwrap();
+        let forecast = build_forecast(data, &place, date).unwrap();
+
+        assert_eq!(forecast.location, "Kyiv, UA");
+        assert_eq!(forecast.date, "2023-03-04");
+        assert_eq!(forecast.min_temp, 1.0);
+        assert_eq!(forecast.max_temp, 6.0);
+        assert_eq!(forecast.avg_temp, Reading::derived(4.0));
+        assert_eq!(forecast.maxwind_kph.value, Some(18.0));
+        assert_eq!(forecast.avghumidity.value, Some(70.0));
+        assert_eq!(forecast.pressure_mb.value, Some(1014.0));
+        assert_eq!(forecast.cloud_cover.value, Some(50.0));
+        assert_eq!(forecast.wind_degree.value, Some(210.0));
+        assert_eq!(forecast.dew_point_c, Reading::derived(-1.0));
+        assert_eq!(forecast.gust_kph, Reading::UNAVAILABLE);
+        // Hourly amount wins over the 6 hours overlapping it.
+        assert_eq!(
+            forecast.precip_mm,
+            Reading::new(Some(1.6), Provenance::Forecast)
+        );
+        assert_eq!(forecast.snow_cm, Reading::derived(0.4));
+        assert_eq!(forecast.condition, Condition::PartlyCloudy);
+        assert_eq!(forecast.condition_text.as_deref(), Some("Partly cloudy"));
+    }
+}
@@ -1,4 +1,23 @@
+pub mod chart;
+pub mod comfort;
+pub mod condition;
+pub mod consensus;
+pub mod daemon;
+pub mod doctor;
+pub mod executor;
+pub mod exporter;
+pub mod history;
+pub mod normals;
+pub mod notify;
+pub mod quota;
+pub mod reading;
+pub mod server;
+pub mod tui;
+pub mod watch;
 pub mod weather_service;
 
+mod geocoding;
+mod metno_strategy;
+mod open_meteo_strategy;
 mod open_weather_strategy;
 mod weatherapi_strategy;
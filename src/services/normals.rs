@@ -0,0 +1,365 @@
+use crate::services::geocoding::{self, Place};
+use crate::services::history::fetch_history;
+use crate::services::open_meteo_strategy::OpenMeteoStrategy;
+use crate::utils::http_cache::cache_dir;
+use crate::utils::meteo;
+use chrono::{Datelike, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/*
+Climate normals are built from daily history of the last N full years (Open-Meteo archive).
+Baseline for a day is taken from all years within a window of days around the same day of year,
+so a single unusual day doesn't make the normal.
+History is downloaded once per location and range and cached in the user's cache directory.
+*/
+
+pub const DEFAULT_YEARS: i32 = 10;
+const WINDOW_DAYS: i64 = 7;
+const DAYS_IN_YEAR: i64 = 365;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Sample {
+    date: String,
+    avg_temp: f32,
+    min_temp: f32,
+    max_temp: f32,
+    precip_mm: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NormalsCache {
+    location: String,
+    samples: Vec<Sample>,
+}
+
+pub struct Normals {
+    pub location: String,
+    pub first_year: i32,
+    pub last_year: i32,
+    samples: Vec<(NaiveDate, Sample)>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct Normal {
+    pub avg_temp: f32,
+    pub min_temp: f32,
+    pub max_temp: f32,
+    pub precip_mm: Option<f32>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct Anomaly {
+    pub normal_temp: f32,
+    pub difference: f32,
+    pub percentile: f32,
+    pub years: i32,
+}
+
+impl Anomaly {
+    pub fn describe(&self) -> String {
+        let direction = if self.difference >= 0.0 {
+            "above"
+        } else {
+            "below"
+        };
+        format!(
+            "{:+}°C {direction} normal (usual {}°C, {} percentile over {} years)",
+            self.difference,
+            self.normal_temp,
+            ordinal(self.percentile.round() as u32),
+            self.years
+        )
+    }
+}
+
+impl Normals {
+    pub fn load(place: &Place, years: i32) -> Result<Self, &'static str> {
+        let last_year = Local::now().year() - 1;
+        let first_year = last_year - years + 1;
+        let from = NaiveDate::from_ymd_opt(first_year, 1, 1).ok_or("Can't build query")?;
+        let to = NaiveDate::from_ymd_opt(last_year, 12, 31).ok_or("Can't build query")?;
+        let path = cache_path(place, from, to);
+
+        let cached = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str::<NormalsCache>(&content).ok());
+        let cache = match cached {
+            Some(cache) => cache,
+            None => {
+                let days =
+                    fetch_history(&OpenMeteoStrategy, &place.location(), Some(place), from, to)?;
+                let cache = NormalsCache {
+                    location: place.location(),
+                    samples: days
+                        .into_iter()
+                        .filter_map(|day| {
+                            Some(Sample {
+                                avg_temp: day.avg_temp.value?,
+                                date: day.date,
+                                min_temp: day.min_temp,
+                                max_temp: day.max_temp,
+                                precip_mm: day.precip_mm.value,
+                            })
+                        })
+                        .collect(),
+                };
+                write_cache(path, &cache);
+                cache
+            }
+        };
+
+        Ok(Self::new(
+            cache.location,
+            first_year,
+            last_year,
+            cache.samples,
+        ))
+    }
+
+    fn new(location: String, first_year: i32, last_year: i32, samples: Vec<Sample>) -> Self {
+        Self {
+            location,
+            first_year,
+            last_year,
+            samples: samples
+                .into_iter()
+                .filter_map(|sample| {
+                    NaiveDate::parse_from_str(&sample.date, "%Y-%m-%d")
+                        .ok()
+                        .map(|date| (date, sample))
+                })
+                .collect(),
+        }
+    }
+
+    fn years(&self) -> i32 {
+        self.last_year - self.first_year + 1
+    }
+
+    fn window(&self, date: NaiveDate) -> Vec<&Sample> {
+        let day = day_of_year(date);
+        self.samples
+            .iter()
+            .filter(|(sample_date, _)| {
+                let distance = (day_of_year(*sample_date) - day).abs();
+                distance.min(DAYS_IN_YEAR - distance) <= WINDOW_DAYS
+            })
+            .map(|(_, sample)| sample)
+            .collect()
+    }
+
+    /// Usual weather around this day of year; precipitation is the usual amount per day.
+    pub fn day_normal(&self, date: NaiveDate) -> Option<Normal> {
+        normal(&self.window(date), 1.0)
+    }
+
+    /// Usual weather in the month; precipitation is the usual monthly total.
+    pub fn month_normal(&self, month: u32) -> Option<Normal> {
+        let samples: Vec<&Sample> = self
+            .samples
+            .iter()
+            .filter(|(date, _)| date.month() == month)
+            .map(|(_, sample)| sample)
+            .collect();
+        let days_per_year = samples.len() as f32 / self.years() as f32;
+        normal(&samples, days_per_year)
+    }
+
+    pub fn anomaly(&self, date: NaiveDate, avg_temp: f32) -> Option<Anomaly> {
+        let window = self.window(date);
+        let normal = normal(&window, 1.0)?;
+        let below = window.iter().filter(|s| s.avg_temp < avg_temp).count() as f32;
+        let equal = window.iter().filter(|s| s.avg_temp == avg_temp).count() as f32;
+
+        Some(Anomaly {
+            normal_temp: normal.avg_temp,
+            difference: meteo::round(avg_temp - normal.avg_temp),
+            percentile: meteo::round((below + equal / 2.0) / window.len() as f32 * 100.0),
+            years: self.years(),
+        })
+    }
+}
+
+pub fn weather_normals(address: &str, month: Option<u32>, years: i32) {
+    let normals = geocoding::geocode(address).and_then(|place| Normals::load(&place, years));
+    let normals = match normals {
+        Ok(normals) => normals,
+        Err(error) => return println!("Error: {error}"),
+    };
+
+    println!(
+        "== Climate normals for {} ({}-{}) ==",
+        normals.location, normals.first_year, normals.last_year
+    );
+    match month {
+        Some(month) => {
+            println!(
+                "{:<6} {:>7} {:>7} {:>7} {:>10}",
+                "Day", "Avg °C", "Min °C", "Max °C", "Precip mm"
+            );
+            let days = (1..=31).filter_map(|day| NaiveDate::from_ymd_opt(2001, month, day));
+            for date in days {
+                if let Some(normal) = normals.day_normal(date) {
+                    render_normal(&date.format("%d-%m").to_string(), &normal);
+                }
+            }
+        }
+        None => {
+            println!(
+                "{:<6} {:>7} {:>7} {:>7} {:>10}",
+                "Month", "Avg °C", "Min °C", "Max °C", "Precip mm"
+            );
+            for month in 1..=12 {
+                if let Some(normal) = normals.month_normal(month) {
+                    let name = NaiveDate::from_ymd_opt(2001, month, 1)
+                        .map(|date| date.format("%b").to_string())
+                        .unwrap_or_default();
+                    render_normal(&name, &normal);
+                }
+            }
+        }
+    }
+}
+
+fn render_normal(label: &str, normal: &Normal) {
+    println!(
+        "{:<6} {:>7} {:>7} {:>7} {:>10}",
+        label,
+        normal.avg_temp,
+        normal.min_temp,
+        normal.max_temp,
+        match normal.precip_mm {
+            Some(precip_mm) => precip_mm.to_string(),
+            None => "".to_string(),
+        }
+    );
+}
+
+fn normal(samples: &[&Sample], days_per_period: f32) -> Option<Normal> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mean = |field: fn(&Sample) -> f32| {
+        meteo::round(samples.iter().map(|sample| field(sample)).sum::<f32>() / samples.len() as f32)
+    };
+    let precip: Vec<f32> = samples.iter().filter_map(|s| s.precip_mm).collect();
+
+    Some(Normal {
+        avg_temp: mean(|sample| sample.avg_temp),
+        min_temp: mean(|sample| sample.min_temp),
+        max_temp: mean(|sample| sample.max_temp),
+        precip_mm: if precip.is_empty() {
+            None
+        } else {
+            Some(meteo::round(
+                precip.iter().sum::<f32>() / precip.len() as f32 * days_per_period,
+            ))
+        },
+    })
+}
+
+// Day of year in a common year, so 29 Feb shares the day with 28 Feb.
+fn day_of_year(date: NaiveDate) -> i64 {
+    let day = if date.month() == 2 {
+        date.day().min(28)
+    } else {
+        date.day()
+    };
+    NaiveDate::from_ymd_opt(2001, date.month(), day)
+        .map(|date| date.ordinal0() as i64)
+        .unwrap_or(0)
+}
+
+fn ordinal(number: u32) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{number}{suffix}")
+}
+
+fn cache_path(place: &Place, from: NaiveDate, to: NaiveDate) -> Option<PathBuf> {
+    cache_dir().map(|dir| {
+        dir.join("normals").join(format!(
+            "{:.4}_{:.4}_{from}_{to}.json",
+            place.latitude, place.longitude
+        ))
+    })
+}
+
+// Cache is best effort: normals are still shown when they can't be written.
+fn write_cache(path: Option<PathBuf>, cache: &NormalsCache) {
+    if let Some(path) = path {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Ok(content) = serde_json::to_string(cache) {
+            let _ = fs::write(path, content);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Normals, Sample};
+    use chrono::{Duration, NaiveDate};
+
+    // Two years of 1 Jan .. 31 Mar where each day of 2021 is 2°C warmer than in 2020
+    fn normals() -> Normals {
+        let mut samples = Vec::new();
+        for (year, shift) in [(2020, 0.0), (2021, 2.0)] {
+            let first = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+            for day in 0..90 {
+                let date = first + Duration::days(day);
+                let avg_temp = day as f32 / 10.0 + shift;
+                samples.push(Sample {
+                    date: date.format("%Y-%m-%d").to_string(),
+                    avg_temp,
+                    min_temp: avg_temp - 4.0,
+                    max_temp: avg_temp + 4.0,
+                    precip_mm: Some(1.0),
+                });
+            }
+        }
+        Normals::new("Kyiv, UA".to_string(), 2020, 2021, samples)
+    }
+
+    #[test]
+    fn day_normal_uses_window_around_day() {
+        let normal = normals()
+            .day_normal(NaiveDate::from_ymd_opt(2023, 2, 1).unwrap())
+            .unwrap();
+        // 32nd day of year (31 from zero): window 24..=38 gives mean 3.1 in 2020 and 5.1 in 2021
+        assert_eq!(normal.avg_temp, 4.1);
+        assert_eq!(normal.min_temp, 0.1);
+        assert_eq!(normal.precip_mm, Some(1.0));
+    }
+
+    #[test]
+    fn month_normal_sums_precipitation() {
+        let normal = normals().month_normal(1).unwrap();
+        assert_eq!(normal.precip_mm, Some(31.0));
+        assert!(normals().month_normal(7).is_none());
+    }
+
+    #[test]
+    fn anomaly_against_normal() {
+        let anomaly = normals()
+            .anomaly(NaiveDate::from_ymd_opt(2023, 2, 1).unwrap(), 8.3)
+            .unwrap();
+        assert_eq!(anomaly.normal_temp, 4.1);
+        assert_eq!(anomaly.difference, 4.2);
+        assert_eq!(anomaly.percentile, 100.0);
+        assert_eq!(anomaly.years, 2);
+        assert_eq!(
+            anomaly.describe(),
+            "+4.2°C above normal (usual 4.1°C, 100th percentile over 2 years)"
+        );
+    }
+}
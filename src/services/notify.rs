@@ -0,0 +1,436 @@
+use crate::services::weather_service::ForecastResponseData;
+use crate::utils::{config, http};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/*
+Sinks deliver the forecast somewhere else than the terminal, they are configured by name:
+
+[sinks.team]
+type = "slack"
+url = "https://hooks.slack.com/services/..."
+
+[sinks.hook]
+type = "webhook"
+url = "https://example.com/weather"
+template = '{"text": "{location}: {condition_text}, up to {max_temp}°C"}'
+
+[sinks.phone]
+type = "ntfy"
+url = "https://ntfy.sh/my-weather"
+
+[sinks.mail]
+type = "email"
+server = "smtp.example.com"
+from = "weather@example.com"
+to = "me@example.com"
+username = "weather@example.com"
+password = "..."
+
+[sinks.script]
+type = "command"
+command = "cat >> ~/weather.ndjson"
+
+Webhook without a template and command get the forecast as JSON, a template can use
+any field of the forecast like `{max_temp}`, and `{json}` for the whole forecast.
+Slack, ntfy and email get a short summary.
+*/
+
+pub trait Sink {
+    fn deliver(&self, forecast: &ForecastResponseData) -> Result<(), &'static str>;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    #[default]
+    Starttls,
+    Tls,
+    /// Plain connection, for a relay on the same host.
+    None,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    Webhook {
+        url: String,
+        template: Option<String>,
+    },
+    Slack {
+        url: String,
+    },
+    Ntfy {
+        url: String,
+    },
+    Email {
+        server: String,
+        port: Option<u16>,
+        #[serde(default)]
+        security: SmtpSecurity,
+        from: String,
+        to: String,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    Command {
+        command: String,
+    },
+}
+
+impl SinkConfig {
+    pub fn sink(&self) -> Box<dyn Sink + '_> {
+        match self {
+            SinkConfig::Webhook { url, template } => Box::new(WebhookSink {
+                url,
+                template: template.as_deref(),
+            }),
+            SinkConfig::Slack { url } => Box::new(SlackSink { url }),
+            SinkConfig::Ntfy { url } => Box::new(NtfySink { url }),
+            SinkConfig::Email { .. } => Box::new(EmailSink { config: self }),
+            SinkConfig::Command { command } => Box::new(CommandSink { command }),
+        }
+    }
+}
+
+struct WebhookSink<'a> {
+    url: &'a str,
+    template: Option<&'a str>,
+}
+
+struct SlackSink<'a> {
+    url: &'a str,
+}
+
+struct NtfySink<'a> {
+    url: &'a str,
+}
+
+struct EmailSink<'a> {
+    config: &'a SinkConfig,
+}
+
+struct CommandSink<'a> {
+    command: &'a str,
+}
+
+impl Sink for WebhookSink<'_> {
+    fn deliver(&self, forecast: &ForecastResponseData) -> Result<(), &'static str> {
+        let body = match self.template {
+            Some(template) => render_template(template, forecast)?,
+            None => to_json(forecast)?,
+        };
+        post(
+            http::blocking_client()?
+                .post(self.url)
+                .header("Content-Type", "application/json")
+                .body(body),
+        )
+    }
+}
+
+impl Sink for SlackSink<'_> {
+    fn deliver(&self, forecast: &ForecastResponseData) -> Result<(), &'static str> {
+        let payload = serde_json::json!({ "text": summary(forecast) });
+        post(http::blocking_client()?.post(self.url).json(&payload))
+    }
+}
+
+impl Sink for NtfySink<'_> {
+    fn deliver(&self, forecast: &ForecastResponseData) -> Result<(), &'static str> {
+        post(
+            http::blocking_client()?
+                .post(self.url)
+                .header("Title", format!("Weather for {}", forecast.location))
+                .header("Tags", forecast.condition.to_string())
+                .body(summary(forecast)),
+        )
+    }
+}
+
+impl Sink for EmailSink<'_> {
+    fn deliver(&self, forecast: &ForecastResponseData) -> Result<(), &'static str> {
+        let (server, port, security, from, to, username, password) = match self.config {
+            SinkConfig::Email {
+                server,
+                port,
+                security,
+                from,
+                to,
+                username,
+                password,
+            } => (server, port, security, from, to, username, password),
+            _ => return Err("Not an email sink"),
+        };
+        let message = Message::builder()
+            .from(
+                from.parse::<Mailbox>()
+                    .map_err(|_| "Incorrect sender address")?,
+            )
+            .to(to
+                .parse::<Mailbox>()
+                .map_err(|_| "Incorrect recipient address")?)
+            .subject(format!(
+                "Weather for {} on {}",
+                forecast.location, forecast.date
+            ))
+            .body(format!("{}\n\n{}", summary(forecast), to_json(forecast)?))
+            .map_err(|_| "Can't build email")?;
+
+        let transport = match security {
+            SmtpSecurity::Starttls => SmtpTransport::starttls_relay(server),
+            SmtpSecurity::Tls => SmtpTransport::relay(server),
+            SmtpSecurity::None => Ok(SmtpTransport::builder_dangerous(server)),
+        }
+        .map_err(|_| "Can't connect to mail server")?;
+        let transport = match port {
+            Some(port) => transport.port(*port),
+            None => transport,
+        };
+        let transport = match (username, password) {
+            (Some(username), Some(password)) => {
+                transport.credentials(Credentials::new(username.clone(), password.clone()))
+            }
+            _ => transport,
+        };
+        match transport.build().send(&message) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Mail server didn't accept the email"),
+        }
+    }
+}
+
+// Forecast is given to the command on stdin.
+impl Sink for CommandSink<'_> {
+    fn deliver(&self, forecast: &ForecastResponseData) -> Result<(), &'static str> {
+        let json = to_json(forecast)?;
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(self.command)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|_| "Can't run notify command")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(stdin, "{json}").map_err(|_| "Can't write forecast to notify command")?;
+        }
+        match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            _ => Err("Notify command failed"),
+        }
+    }
+}
+
+fn post(request: reqwest::blocking::RequestBuilder) -> Result<(), &'static str> {
+    match request.send() {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) if response.status().as_u16() == 407 => Err("Proxy refused the credentials"),
+        Ok(_) => Err("Notification was rejected by the server"),
+        Err(error) => Err(http::proxy_error(&error).unwrap_or("Can't send notification")),
+    }
+}
+
+fn to_json(forecast: &ForecastResponseData) -> Result<String, &'static str> {
+    serde_json::to_string(forecast).map_err(|_| "Can't serialize forecast")
+}
+
+fn summary(forecast: &ForecastResponseData) -> String {
+    let condition = match &forecast.condition_text {
+        Some(text) => text.clone(),
+        None => forecast.condition.to_string(),
+    };
+    let mut text = format!(
+        "{} on {}: {condition}, {}..{}°C",
+        forecast.location, forecast.date, forecast.min_temp, forecast.max_temp
+    );
+    if let Some(chance) = forecast.precip_chance.value {
+        text.push_str(&format!(", {chance}% chance of precipitation"));
+    }
+    text
+}
+
+// Placeholders are put into JSON strings, so the values are escaped.
+fn render_template(
+    template: &str,
+    forecast: &ForecastResponseData,
+) -> Result<String, &'static str> {
+    let fields = match serde_json::to_value(forecast) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => return Err("Can't serialize forecast"),
+    };
+    let mut text = template.replace("{json}", &to_json(forecast)?);
+    for (name, value) in fields {
+        // Readings are shown by their value.
+        let value = match value.get("value") {
+            Some(reading) => reading.clone(),
+            None => value,
+        };
+        let value = match value {
+            serde_json::Value::Null => "".to_string(),
+            serde_json::Value::String(text) => escape(&text),
+            value => escape(&value.to_string()),
+        };
+        text = text.replace(&format!("{{{name}}}"), &value);
+    }
+    Ok(text)
+}
+
+fn escape(text: &str) -> String {
+    let quoted = serde_json::Value::String(text.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+/// Delivers the forecast to the sink configured under this name.
+pub fn notify(name: &str, forecast: &ForecastResponseData) -> Result<(), String> {
+    let config = config::load();
+    match config.sinks.get(name) {
+        Some(sink) => sink
+            .sink()
+            .deliver(forecast)
+            .map_err(|error| format!("{name}: {error}")),
+        None => Err(format!("{name}: No such sink in the config")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_template, summary, SinkConfig};
+    use crate::services::condition::Condition;
+    use crate::services::reading::{Provenance, Reading};
+    use crate::services::weather_service::ForecastResponseData;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Accepts one request and answers with the status, returns the request head and body.
+    fn stub_server(status: u16) -> (String, thread::JoinHandle<(String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|length| length.trim().parse::<usize>().unwrap())
+                })
+                .unwrap_or(0);
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 {status} OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            )
+            .unwrap();
+            (head, String::from_utf8(body).unwrap())
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn webhook_posts_forecast_json() {
+        let (url, server) = stub_server(200);
+        let sink = SinkConfig::Webhook {
+            url,
+            template: None,
+        };
+        sink.sink().deliver(&forecast()).unwrap();
+
+        let (head, body) = server.join().unwrap();
+        assert!(head.starts_with("POST /hook"));
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["location"], "Kyiv, UA");
+        assert_eq!(json["precip_mm"]["value"], 2.5);
+    }
+
+    #[test]
+    fn webhook_with_template() {
+        let (url, server) = stub_server(200);
+        let sink = SinkConfig::Webhook {
+            url,
+            template: Some(r#"{"text": "{location}: {condition_text} {max_temp}"}"#.to_string()),
+        };
+        sink.sink().deliver(&forecast()).unwrap();
+
+        let (_, body) = server.join().unwrap();
+        assert_eq!(body, r#"{"text": "Kyiv, UA: Light \"rain\" 6.5"}"#);
+    }
+
+    #[test]
+    fn slack_and_ntfy_get_summary() {
+        let (url, server) = stub_server(200);
+        SinkConfig::Slack { url }
+            .sink()
+            .deliver(&forecast())
+            .unwrap();
+        let (_, body) = server.join().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["text"], summary(&forecast()));
+
+        let (url, server) = stub_server(200);
+        SinkConfig::Ntfy { url }
+            .sink()
+            .deliver(&forecast())
+            .unwrap();
+        let (head, body) = server.join().unwrap();
+        assert!(head.contains("title: Weather for Kyiv, UA"));
+        assert_eq!(body, summary(&forecast()));
+    }
+
+    #[test]
+    fn rejected_notification_is_error() {
+        let (url, server) = stub_server(500);
+        let result = SinkConfig::Slack { url }.sink().deliver(&forecast());
+        server.join().unwrap();
+        assert_eq!(result, Err("Notification was rejected by the server"));
+    }
+
+    #[test]
+    fn command_gets_forecast_on_stdin() {
+        let sink = SinkConfig::Command {
+            command: "grep -q '\"location\":\"Kyiv, UA\"'".to_string(),
+        };
+        assert_eq!(sink.sink().deliver(&forecast()), Ok(()));
+        let sink = SinkConfig::Command {
+            command: "grep -q Lisbon".to_string(),
+        };
+        assert_eq!(
+            sink.sink().deliver(&forecast()),
+            Err("Notify command failed")
+        );
+    }
+
+    #[test]
+    fn template_with_missing_value() {
+        let text = render_template("{snow_cm}|{min_temp}|{unknown}", &forecast()).unwrap();
+        assert_eq!(text, "|1.5|{unknown}");
+    }
+
+    fn forecast() -> ForecastResponseData {
+        let forecast = |value: f32| Reading::new(Some(value), Provenance::Forecast);
+        ForecastResponseData {
+            avg_temp: forecast(4.0),
+            maxwind_kph: forecast(20.0),
+            avghumidity: forecast(90.0),
+            condition: Condition::Rain,
+            condition_text: Some("Light \"rain\"".to_string()),
+            precip_mm: forecast(2.5),
+            precip_chance: forecast(80.0),
+            ..ForecastResponseData::sample("Kyiv, UA", "2025-05-05", 1.5, 6.5)
+        }
+    }
+}
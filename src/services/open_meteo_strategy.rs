@@ -0,0 +1,295 @@
+use crate::services::condition::Condition;
+use crate::services::geocoding::Place;
+use crate::services::history::HistoryStrategy;
+use crate::services::reading::{Provenance, Reading};
+use crate::services::weather_service::{ForecastResponseData, Source};
+use crate::utils::date::Zone;
+use crate::utils::http::HttpError;
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/*
+Open-Meteo is keyless and works with coordinates, daily values are aggregated in the location's
+time zone (`timezone=auto`). Only its reanalysis archive since 1940 is used, for long history
+and climate normals:
+
+https://archive-api.open-meteo.com/v1/archive?latitude=50.45&longitude=30.52&daily=...&start_date=2013-01-01&end_date=2013-12-31&timezone=auto
+
+Weather is described with WMO codes.
+*/
+pub struct OpenMeteoStrategy;
+
+pub const ARCHIVE_URL: &str = "https://archive-api.open-meteo.com/v1/archive";
+const DAILY: &str = "weather_code,temperature_2m_max,temperature_2m_min,temperature_2m_mean,precipitation_sum,snowfall_sum,wind_speed_10m_max,wind_gusts_10m_max,wind_direction_10m_dominant,relative_humidity_2m_mean,pressure_msl_mean,cloud_cover_mean,dew_point_2m_mean";
+const ARCHIVE_DAYS_PER_REQUEST: i64 = 366;
+
+impl HistoryStrategy for OpenMeteoStrategy {
+    fn provider(&self) -> Source {
+        Source::OpenMeteo
+    }
+
+    fn parse_error(&self, status: u16, body: &str) -> &'static str {
+        parse_error(status, body)
+    }
+
+    fn earliest_date(&self) -> NaiveDate {
+        NaiveDate::from_ymd_opt(1940, 1, 1).expect("valid date")
+    }
+
+    fn max_days_per_request(&self) -> i64 {
+        ARCHIVE_DAYS_PER_REQUEST
+    }
+
+    fn build_history_request(
+        &self,
+        _address: &str,
+        place: Option<&Place>,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<String, &'static str> {
+        match place {
+            Some(place) => query(ARCHIVE_URL, DAILY, place, from, to),
+            None => Err("No location found"),
+        }
+    }
+
+    fn build_history_response(
+        &self,
+        place: Option<&Place>,
+        body: &str,
+    ) -> Result<Vec<ForecastResponseData>, &'static str> {
+        match place {
+            Some(place) => parse_days(body, place, Provenance::Measured),
+            None => Err("No location found"),
+        }
+    }
+}
+
+// Errors come as `{"error": true, "reason": "Parameter 'start_date' is out of allowed range ..."}`.
+fn parse_error(status: u16, body: &str) -> &'static str {
+    let reason = serde_json::from_str::<ErrorResponse>(body)
+        .map(|error| error.reason)
+        .unwrap_or_default();
+    match reason.as_str() {
+        reason if reason.contains("out of allowed range") => {
+            "Sorry, selected service doesn't support this date"
+        }
+        "" => HttpError::status_error(status),
+        _ => "Weather service rejected the request parameters",
+    }
+}
+
+fn parse_days(
+    body: &str,
+    place: &Place,
+    provenance: Provenance,
+) -> Result<Vec<ForecastResponseData>, &'static str> {
+    match serde_json::from_str::<OpenMeteoResponse>(body) {
+        Ok(data) => Ok(build_days(data, place, provenance)),
+        Err(_) => Err("Incorrect data from weather service: can't parse"),
+    }
+}
+
+fn query(
+    url: &str,
+    daily: &str,
+    place: &Place,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<String, &'static str> {
+    match url::Url::parse(url) {
+        Ok(mut url) => {
+            url.query_pairs_mut()
+                .append_pair("latitude", &format!("{:.4}", place.latitude))
+                .append_pair("longitude", &format!("{:.4}", place.longitude))
+                .append_pair("daily", daily)
+                .append_pair("start_date", &from.format("%Y-%m-%d").to_string())
+                .append_pair("end_date", &to.format("%Y-%m-%d").to_string())
+                .append_pair("timezone", "auto");
+            Ok(url.to_string())
+        }
+        Err(_) => Err("Can't build query"),
+    }
+}
+
+// Days without temperature (archive isn't complete for the last days) are skipped.
+fn build_days(
+    data: OpenMeteoResponse,
+    place: &Place,
+    provenance: Provenance,
+) -> Vec<ForecastResponseData> {
+    let daily = data.daily;
+    let timezone = data.timezone.parse::<Tz>().ok().map(Zone::from);
+    let value = |values: &[Option<f32>], index: usize| values.get(index).copied().flatten();
+    let reading =
+        |values: &[Option<f32>], index: usize| Reading::new(value(values, index), provenance);
+
+    daily
+        .time
+        .iter()
+        .enumerate()
+        .filter_map(|(index, date)| {
+            let max_temp = value(&daily.temperature_2m_max, index)?;
+            let min_temp = value(&daily.temperature_2m_min, index)?;
+            let condition = daily
+                .weather_code
+                .get(index)
+                .copied()
+                .flatten()
+                .map(Condition::from_wmo_code)
+                .unwrap_or(Condition::Unknown);
+
+            Some(ForecastResponseData {
+                location: place.location(),
+                date: date.clone(),
+                max_temp,
+                min_temp,
+                avg_temp: match value(&daily.temperature_2m_mean, index) {
+                    Some(avg_temp) => Reading::new(Some(avg_temp), provenance),
+                    None => Reading::derived((max_temp + min_temp) / 2.0),
+                },
+                maxwind_kph: reading(&daily.wind_speed_10m_max, index),
+                avghumidity: reading(&daily.relative_humidity_2m_mean, index),
+                pressure_mb: reading(&daily.pressure_msl_mean, index),
+                // Archive has no UV and no probabilities.
+                uv_index: Reading::UNAVAILABLE,
+                visibility_km: Reading::UNAVAILABLE,
+                dew_point_c: reading(&daily.dew_point_2m_mean, index),
+                cloud_cover: reading(&daily.cloud_cover_mean, index),
+                wind_degree: reading(&daily.wind_direction_10m_dominant, index),
+                gust_kph: reading(&daily.wind_gusts_10m_max, index),
+                condition,
+                condition_text: match condition {
+                    Condition::Unknown => None,
+                    condition => Some(capitalize(condition.name())),
+                },
+                precip_mm: reading(&daily.precipitation_sum, index),
+                precip_chance: Reading::UNAVAILABLE,
+                snow_cm: reading(&daily.snowfall_sum, index),
+                timezone,
+                normal: None,
+            })
+        })
+        .collect()
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        None => "".to_string(),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OpenMeteoResponse {
+    #[serde(default)]
+    timezone: String,
+    daily: Daily,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Daily {
+    time: Vec<String>,
+    #[serde(default)]
+    weather_code: Vec<Option<u32>>,
+    #[serde(default)]
+    temperature_2m_max: Vec<Option<f32>>,
+    #[serde(default)]
+    temperature_2m_min: Vec<Option<f32>>,
+    #[serde(default)]
+    temperature_2m_mean: Vec<Option<f32>>,
+    #[serde(default)]
+    precipitation_sum: Vec<Option<f32>>,
+    #[serde(default)]
+    snowfall_sum: Vec<Option<f32>>,
+    #[serde(default)]
+    wind_speed_10m_max: Vec<Option<f32>>,
+    #[serde(default)]
+    wind_gusts_10m_max: Vec<Option<f32>>,
+    #[serde(default)]
+    wind_direction_10m_dominant: Vec<Option<f32>>,
+    #[serde(default)]
+    relative_humidity_2m_mean: Vec<Option<f32>>,
+    #[serde(default)]
+    pressure_msl_mean: Vec<Option<f32>>,
+    #[serde(default)]
+    cloud_cover_mean: Vec<Option<f32>>,
+    #[serde(default)]
+    dew_point_2m_mean: Vec<Option<f32>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_days, parse_error, OpenMeteoResponse};
+    use crate::services::condition::Condition;
+    use crate::services::geocoding::Place;
+    use crate::services::reading::{Provenance, Reading};
+
+    #[test]
+    fn builds_daily_values() {
+        let place = Place {
+            name: "Kyiv".to_string(),
+            country: "UA".to_string(),
+            latitude: 50.45,
+            longitude: 30.5236,
+            timezone: chrono_tz::Europe::Kyiv,
+        };
+        let body = r#"{"timezone": "Europe/Kyiv", "daily": {
+            "time": ["2013-01-01", "2013-01-02", "2013-01-03"],
+            "weather_code": [73, 3, null],
+            "temperature_2m_max": [-1.5, 2.0, null],
+            "temperature_2m_min": [-6.5, -1.0, null],
+            "temperature_2m_mean": [-4.0, null, null],
+            "precipitation_sum": [3.2, 0.0, null],
+            "snowfall_sum": [2.24, 0.0, null],
+            "wind_speed_10m_max": [18.4, 9.0, null],
+            "wind_gusts_10m_max": [35.3, 20.1, null],
+            "wind_direction_10m_dominant": [290, 180, null],
+            "relative_humidity_2m_mean": [90.0, 75.0, null]
+        }}"#;
+        let data: OpenMeteoResponse = serde_json::from_str(body).unwrap();
+        let days = build_days(data, &place, Provenance::Measured);
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].date, "2013-01-01");
+        assert_eq!(
+            days[0].avg_temp,
+            Reading::new(Some(-4.0), Provenance::Measured)
+        );
+        assert_eq!(days[0].precip_mm.value, Some(3.2));
+        assert_eq!(days[0].snow_cm.value, Some(2.24));
+        assert_eq!(days[0].precip_chance, Reading::UNAVAILABLE);
+        assert_eq!(days[0].gust_kph.value, Some(35.3));
+        assert_eq!(days[0].wind_degree.value, Some(290.0));
+        assert_eq!(days[0].pressure_mb, Reading::UNAVAILABLE);
+        assert_eq!(days[0].condition, Condition::Snow);
+        assert_eq!(days[0].condition_text.as_deref(), Some("Snow"));
+        assert_eq!(days[1].avg_temp, Reading::derived(0.5));
+        assert_eq!(days[1].condition, Condition::Overcast);
+    }
+
+    #[test]
+    fn error_payloads() {
+        let body = r#"{"error":true,"reason":"Parameter 'start_date' is out of allowed range from 2016-01-01 to 2025-06-30"}"#;
+        assert_eq!(
+            parse_error(400, body),
+            "Sorry, selected service doesn't support this date"
+        );
+        let body = r#"{"error":true,"reason":"Cannot initialize WeatherVariable from invalid String value tempeture_2m"}"#;
+        assert_eq!(
+            parse_error(400, body),
+            "Weather service rejected the request parameters"
+        );
+        assert_eq!(
+            parse_error(429, ""),
+            "Weather service is throttling requests"
+        );
+    }
+}
@@ -1,5 +1,12 @@
-use crate::services::weather_service::{ForecastResponseData, ForecastStrategy};
-use crate::utils::date::DateOption;
+use crate::services::condition::Condition;
+use crate::services::reading::{Provenance, Reading};
+use crate::services::weather_service::{
+    ForecastQuery, ForecastResponseData, ForecastStrategy, ForecastTarget, WeatherService,
+};
+use crate::utils::date::{DateOption, Zone};
+use crate::utils::http::HttpError;
+use crate::utils::meteo;
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 pub struct OpenWeatherStrategy;
 
@@ -11,57 +18,140 @@ https://api.openweathermap.org/data/2.5/forecast/daily?q=Kyiv,%20UA&cnt=16&units
 Historical data isn't available as well.
 */
 
-const API_KEY: &str = env!("OPENWEATHER_API_KEY");
-const URL: &str = "https://api.openweathermap.org/data/2.5/forecast/daily";
+pub const API_KEY: &str = env!("OPENWEATHER_API_KEY");
+pub const URL: &str = "https://api.openweathermap.org/data/2.5/forecast/daily";
 
 impl ForecastStrategy for OpenWeatherStrategy {
-    fn build_request(&self, address: &str, date: &str) -> Result<String, &'static str> {
-        let date = DateOption::new(date);
-        match WeatherRequest::new(address, date).query(API_KEY) {
-            Ok(query) => Ok(query),
-            Err(error) => {
-                println!("{error}");
-                Err(error)
-            }
-        }
+    fn provider(&self) -> WeatherService {
+        WeatherService::OpenWeather
+    }
+
+    fn parse_error(&self, status: u16, body: &str) -> &'static str {
+        parse_error(status, body)
+    }
+
+    fn build_request(&self, target: &ForecastTarget) -> Result<ForecastQuery, &'static str> {
+        let request = WeatherRequest::new(&target.address, target.date_option.clone());
+        let url = request.query(API_KEY)?;
+        Ok(ForecastQuery {
+            url,
+            date_option: request.date_option,
+            place: None,
+        })
+    }
+
+    fn zone(&self, body: &str) -> Option<Zone> {
+        let data = serde_json::from_str::<WeatherApiResponse>(body).ok()?;
+        data.city.timezone.and_then(Zone::from_offset_seconds)
     }
 
     fn build_response(
         &self,
-        request_result: reqwest::blocking::Response,
+        query: &ForecastQuery,
+        body: &str,
     ) -> Result<ForecastResponseData, &'static str> {
-        match request_result.json::<WeatherApiResponse>() {
-            Ok(data) => build_forecast(data),
+        match serde_json::from_str::<WeatherApiResponse>(body) {
+            Ok(data) => build_forecast(data, query.date_option.date),
             Err(_) => Err("Incorrect data from weather service: can't parse"),
         }
     }
 }
 
-fn build_forecast(data: WeatherApiResponse) -> Result<ForecastResponseData, &'static str> {
-    let weatherday = match data.list.last() {
+// `dt` is UTC timestamp, `city.timezone` is shift in seconds from UTC of the location.
+fn build_forecast(
+    data: WeatherApiResponse,
+    date: NaiveDate,
+) -> Result<ForecastResponseData, &'static str> {
+    let timezone = data.city.timezone.and_then(Zone::from_offset_seconds);
+    let local_date = |dt: i64| {
+        chrono::DateTime::from_timestamp(dt, 0).map(|time| match timezone {
+            Some(timezone) => timezone.local(time).date(),
+            None => time.date_naive(),
+        })
+    };
+    let weatherday = match data
+        .list
+        .iter()
+        .find(|day| local_date(day.dt) == Some(date))
+    {
         Some(weatherday) => weatherday,
         None => return Err("Error: No forecast for this day"),
     };
-    let date =
-        chrono::NaiveDateTime::from_timestamp_opt(weatherday.dt, 0).expect("error decoding date");
-    println!("DATE: {}", weatherday.dt);
-    let date_string = date.format("%Y-%m-%d").to_string();
+
+    let weather = weatherday.weather.first();
+    let forecast = |value: Option<f32>| Reading::new(value, Provenance::Forecast);
 
     Ok(ForecastResponseData {
         location: format!("{}, {}", data.city.name, data.city.country),
-        date: date_string,
+        date: date.format("%Y-%m-%d").to_string(),
         max_temp: weatherday.temp.max,
         min_temp: weatherday.temp.min,
-        avg_temp: weatherday.temp.day,
-        maxwind_kph: weatherday.speed,
-        avghumidity: weatherday.humidity,
-        condition: match weatherday.weather.first() {
-            Some(weather) => weather.main.clone(),
-            None => "".to_string(),
+        avg_temp: average_temp(&weatherday.temp),
+        // Wind speed comes in meter/sec with metric units.
+        maxwind_kph: forecast(Some(meteo::round(weatherday.speed * meteo::MPS_TO_KPH))),
+        avghumidity: forecast(Some(weatherday.humidity)),
+        pressure_mb: forecast(weatherday.pressure),
+        uv_index: Reading::UNAVAILABLE,
+        visibility_km: Reading::UNAVAILABLE,
+        dew_point_c: Reading::new(
+            meteo::dew_point(weatherday.temp.day, weatherday.humidity),
+            Provenance::Derived,
+        ),
+        cloud_cover: forecast(weatherday.clouds),
+        wind_degree: forecast(weatherday.deg),
+        gust_kph: forecast(
+            weatherday
+                .gust
+                .map(|gust| meteo::round(gust * meteo::MPS_TO_KPH)),
+        ),
+        condition: match weather {
+            Some(weather) => Condition::from_openweather_id(weather.id),
+            None => Condition::Unknown,
         },
+        condition_text: weather.map(|weather| weather.main.clone()),
+        precip_mm: match (weatherday.rain, weatherday.snow) {
+            (None, None) if weatherday.pop.is_none() => Reading::UNAVAILABLE,
+            (rain, snow) => forecast(Some(rain.unwrap_or(0.0) + snow.unwrap_or(0.0))),
+        },
+        precip_chance: forecast(weatherday.pop.map(|pop| (pop * 100.0).round())),
+        // `snow` is water equivalent in mm.
+        snow_cm: Reading::new(
+            weatherday
+                .snow
+                .map(|snow| (snow * meteo::SNOW_PER_LIQUID).round() / 10.0),
+            Provenance::Derived,
+        ),
+        timezone,
+        normal: None,
     })
 }
 
+// `temp.day` is the temperature at midday, the average is derived from the four parts of the day.
+fn average_temp(temp: &Temp) -> Reading {
+    match (temp.morn, temp.eve, temp.night) {
+        (Some(morn), Some(eve), Some(night)) => {
+            Reading::derived(((morn + temp.day + eve + night) / 4.0 * 10.0).round() / 10.0)
+        }
+        _ => Reading::UNAVAILABLE,
+    }
+}
+
+// Errors come as `{"cod": "404", "message": "city not found"}`, `cod` is sometimes a number.
+fn parse_error(status: u16, body: &str) -> &'static str {
+    let message = serde_json::from_str::<ErrorResponse>(body)
+        .map(|error| error.message.to_lowercase())
+        .unwrap_or_default();
+    match (status, message.as_str()) {
+        (_, "city not found" | "nothing to geocode") => "No location found",
+        (status, _) => HttpError::status_error(status),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    message: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct WeatherApiResponse {
     city: City,
@@ -72,6 +162,7 @@ struct WeatherApiResponse {
 struct City {
     name: String,
     country: String,
+    timezone: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -81,6 +172,21 @@ struct Day {
     humidity: f32,
     weather: Vec<DayWeather>,
     speed: f32,
+    #[serde(default)]
+    deg: Option<f32>,
+    #[serde(default)]
+    gust: Option<f32>,
+    #[serde(default)]
+    pressure: Option<f32>,
+    #[serde(default)]
+    clouds: Option<f32>,
+    // `rain` and `snow` are volumes in mm, omitted when nothing is expected; `pop` is 0..1
+    #[serde(default)]
+    pop: Option<f32>,
+    #[serde(default)]
+    rain: Option<f32>,
+    #[serde(default)]
+    snow: Option<f32>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -88,10 +194,17 @@ struct Temp {
     day: f32,
     min: f32,
     max: f32,
+    #[serde(default)]
+    morn: Option<f32>,
+    #[serde(default)]
+    eve: Option<f32>,
+    #[serde(default)]
+    night: Option<f32>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct DayWeather {
+    id: u32,
     main: String,
 }
 
@@ -114,7 +227,11 @@ impl WeatherRequest {
                 Ok(mut url) => {
                     url.query_pairs_mut()
                         .append_pair("q", &self.address)
-                        .append_pair("cnt", &(&self.date_option.days_from_now + 1).to_string())
+                        // One day more, until the response has the zone the location may be a day behind.
+                        .append_pair(
+                            "cnt",
+                            &(self.date_option.days_from_now + 2).min(17).to_string(),
+                        )
                         .append_pair("units", "metric")
                         .append_pair("appid", api_key);
                     Ok(url.to_string())
@@ -130,3 +247,67 @@ impl WeatherRequest {
         (1..=17).contains(&(self.date_option.days_from_now + 1))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{build_forecast, parse_error, WeatherApiResponse};
+    use crate::services::reading::Reading;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn builds_forecast_without_fabricated_values() {
+        let body = r#"{"city": {"name": "Kyiv", "country": "UA", "timezone": 7200}, "list": [
+            {"dt": 1741514400, "temp": {"day": 4.0, "min": 1.0, "max": 6.0, "morn": 2.0, "eve": 5.0, "night": 3.0}, "humidity": 80,
+             "weather": [{"id": 500, "main": "Rain"}], "speed": 5.0, "deg": 315, "gust": 9.5,
+             "pressure": 1012, "clouds": 90, "pop": 0.64, "rain": 3.1},
+            {"dt": 1741600800, "temp": {"day": 2.0, "min": -1.0, "max": 3.0}, "humidity": 70,
+             "weather": [{"id": 800, "main": "Clear"}], "speed": 3.0},
+            {"dt": 1741687200, "temp": {"day": -2.0, "min": -4.0, "max": 0.0}, "humidity": 90,
+             "weather": [{"id": 601, "main": "Snow"}], "speed": 4.0, "pop": 0.9, "snow": 2.4}
+        ]}"#;
+        let data: WeatherApiResponse = serde_json::from_str(body).unwrap();
+        let date = NaiveDate::from_ymd_opt(2025, 3, 9).unwrap();
+        let forecast = build_forecast(data, date).unwrap();
+
+        assert_eq!(forecast.avg_temp, Reading::derived(3.5));
+        assert_eq!(forecast.maxwind_kph.value, Some(18.0));
+        assert_eq!(forecast.precip_mm.value, Some(3.1));
+        assert_eq!(forecast.precip_chance.value, Some(64.0));
+        assert_eq!(forecast.snow_cm, Reading::UNAVAILABLE);
+        assert_eq!(forecast.gust_kph.value, Some(34.2));
+        assert_eq!(forecast.wind_degree.value, Some(315.0));
+        assert_eq!(forecast.dew_point_c, Reading::derived(0.9));
+        assert_eq!(forecast.visibility_km, Reading::UNAVAILABLE);
+
+        let data: WeatherApiResponse = serde_json::from_str(body).unwrap();
+        let date = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let forecast = build_forecast(data, date).unwrap();
+        assert_eq!(forecast.avg_temp, Reading::UNAVAILABLE);
+        assert_eq!(forecast.precip_mm, Reading::UNAVAILABLE);
+
+        let data: WeatherApiResponse = serde_json::from_str(body).unwrap();
+        let date = NaiveDate::from_ymd_opt(2025, 3, 11).unwrap();
+        let forecast = build_forecast(data, date).unwrap();
+        assert_eq!(forecast.precip_mm.value, Some(2.4));
+        assert_eq!(forecast.snow_cm, Reading::derived(2.4));
+    }
+
+    #[test]
+    fn error_payloads() {
+        assert_eq!(
+            parse_error(404, r#"{"cod":"404","message":"city not found"}"#),
+            "No location found"
+        );
+        assert_eq!(
+            parse_error(
+                401,
+                r#"{"cod":401,"message":"Invalid API key. Please see https://openweathermap.org/faq#error401 for more info."}"#
+            ),
+            "Weather service refused the API key"
+        );
+        assert_eq!(
+            parse_error(502, "<html>Bad Gateway</html>"),
+            "Weather service returned an error"
+        );
+    }
+}
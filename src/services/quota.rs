@@ -0,0 +1,358 @@
+use crate::services::weather_service::{Source, WeatherService};
+use crate::utils::config;
+use crate::utils::http_cache::cache_dir;
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/*
+Free plans limit the number of requests, so every request to a provider is counted in a ledger
+in the cache directory before it's sent, and refused when it would go over the limit:
+
+[quota.openweather]
+per_minute = 60
+per_month = 1000000
+
+[quota.openmeteo]
+per_day = 10000
+
+Requests per minute are a token bucket: a full minute of requests can be sent at once and then
+one more every 60/per_minute seconds. Days and months are calendar ones in UTC.
+A configured provider replaces its defaults, a limit which isn't set isn't checked.
+
+Several processes (a daemon and a cron job) share the ledger, so it's changed under an OS lock
+of `quota.json.lock` and replaced by rename, readers never see a half written file.
+*/
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct QuotaLimit {
+    pub per_minute: Option<u32>,
+    pub per_day: Option<u32>,
+    pub per_month: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+struct Usage {
+    /// Requests left in the minute bucket and when it was filled last time, unix milliseconds.
+    tokens: f64,
+    refilled: Option<i64>,
+    day: String,
+    day_count: u32,
+    month: String,
+    month_count: u32,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Ledger {
+    providers: BTreeMap<String, Usage>,
+}
+
+impl QuotaLimit {
+    /// Limits of free plans.
+    pub fn default_for(source: Source) -> Self {
+        match source {
+            Source::Forecast(WeatherService::OpenWeather) => QuotaLimit {
+                per_minute: Some(60),
+                per_day: None,
+                per_month: Some(1_000_000),
+            },
+            Source::Forecast(WeatherService::WeatherApi) => QuotaLimit {
+                per_minute: None,
+                per_day: None,
+                per_month: Some(1_000_000),
+            },
+            Source::OpenMeteo => QuotaLimit {
+                per_minute: Some(600),
+                per_day: Some(10_000),
+                per_month: Some(300_000),
+            },
+            _ => QuotaLimit::default(),
+        }
+    }
+}
+
+impl Usage {
+    fn refill(&mut self, limit: &QuotaLimit, now: DateTime<Utc>) {
+        let day = now.format("%Y-%m-%d").to_string();
+        if self.day != day {
+            self.day = day;
+            self.day_count = 0;
+        }
+        let month = now.format("%Y-%m").to_string();
+        if self.month != month {
+            self.month = month;
+            self.month_count = 0;
+        }
+        if let Some(per_minute) = limit.per_minute {
+            let capacity = f64::from(per_minute);
+            self.tokens = match self.refilled {
+                Some(refilled) => {
+                    let seconds = (now.timestamp_millis() - refilled).max(0) as f64 / 1000.0;
+                    (self.tokens + seconds * capacity / 60.0).min(capacity)
+                }
+                None => capacity,
+            };
+            self.refilled = Some(now.timestamp_millis());
+        }
+    }
+
+    fn take(&mut self, limit: &QuotaLimit, now: DateTime<Utc>) -> Result<(), &'static str> {
+        self.refill(limit, now);
+        if limit.per_minute.is_some() && self.tokens < 1.0 {
+            return Err("Request limit per minute of the provider is reached, try again later");
+        }
+        if limit
+            .per_day
+            .is_some_and(|per_day| self.day_count >= per_day)
+        {
+            return Err("Daily request limit of the provider is reached");
+        }
+        if limit
+            .per_month
+            .is_some_and(|per_month| self.month_count >= per_month)
+        {
+            return Err("Monthly request limit of the provider is reached");
+        }
+        if limit.per_minute.is_some() {
+            self.tokens -= 1.0;
+        }
+        self.day_count += 1;
+        self.month_count += 1;
+        Ok(())
+    }
+}
+
+fn limits(configured: &HashMap<String, QuotaLimit>, source: Source) -> QuotaLimit {
+    match configured.get(source.name()) {
+        Some(limit) => limit.clone(),
+        None => QuotaLimit::default_for(source),
+    }
+}
+
+// Config is read once per process, not for every request.
+fn configured() -> &'static HashMap<String, QuotaLimit> {
+    static CONFIGURED: OnceLock<HashMap<String, QuotaLimit>> = OnceLock::new();
+    CONFIGURED.get_or_init(|| config::load().quota)
+}
+
+/// Counts one request to the source, or refuses it when a limit is reached.
+pub fn take(source: Source) -> Result<(), &'static str> {
+    let limit = limits(configured(), source);
+    match ledger_path() {
+        Some(path) => update_ledger(&path, |ledger| {
+            let usage = ledger
+                .providers
+                .entry(source.name().to_string())
+                .or_default();
+            usage.take(&limit, Utc::now())
+        }),
+        None => Ok(()),
+    }
+}
+
+pub fn weather_quota() {
+    let configured = configured();
+    let now = Utc::now();
+    let mut ledger = ledger_path()
+        .map(|path| load_ledger(&path))
+        .unwrap_or_default();
+    let used = |count: u32, limit: Option<u32>| match limit {
+        Some(limit) => format!("{count}/{limit}"),
+        None => format!("{count}"),
+    };
+
+    println!(
+        "{:<12} {:>14} {:>16} {:>18}",
+        "provider", "minute left", "today", "this month"
+    );
+    // Open-Meteo isn't a forecast provider, but history and normals use it.
+    for provider in WeatherService::ALL
+        .into_iter()
+        .map(Source::from)
+        .chain([Source::OpenMeteo])
+    {
+        let limit = limits(configured, provider);
+        let usage = ledger
+            .providers
+            .entry(provider.name().to_string())
+            .or_default();
+        usage.refill(&limit, now);
+        let minute = match limit.per_minute {
+            Some(per_minute) => format!("{}/{per_minute}", usage.tokens.floor()),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:<12} {:>14} {:>16} {:>18}",
+            provider.name(),
+            minute,
+            used(usage.day_count, limit.per_day),
+            used(usage.month_count, limit.per_month)
+        );
+    }
+}
+
+fn ledger_path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("quota.json"))
+}
+
+fn load_ledger(path: &Path) -> Ledger {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Changes the ledger at `path` while no other process or thread does, the change is saved
+/// only when `update` succeeds. Ledger is best effort: the request is still sent when it can't
+/// be locked or written.
+fn update_ledger<T, E>(
+    path: &Path,
+    update: impl FnOnce(&mut Ledger) -> Result<T, E>,
+) -> Result<T, E> {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    // Lock is released when the file is closed.
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("json.lock"))
+        .and_then(|lock| lock.lock_exclusive().map(|_| lock));
+    let mut ledger = load_ledger(path);
+    let result = update(&mut ledger)?;
+    if let Ok(content) = serde_json::to_string(&ledger) {
+        let saved = path.with_extension("json.tmp");
+        if fs::write(&saved, content).is_ok() {
+            let _ = fs::rename(saved, path);
+        }
+    }
+    drop(lock);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{limits, load_ledger, update_ledger, QuotaLimit, Usage};
+    use crate::services::weather_service::WeatherService;
+    use chrono::{DateTime, Duration, Utc};
+    use std::collections::HashMap;
+
+    fn time(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().into()
+    }
+
+    #[test]
+    fn minute_bucket_refills_over_time() {
+        let limit = QuotaLimit {
+            per_minute: Some(3),
+            ..QuotaLimit::default()
+        };
+        let mut usage = Usage::default();
+        let now = time("2025-03-01T10:00:00Z");
+        for _ in 0..3 {
+            assert_eq!(usage.take(&limit, now), Ok(()));
+        }
+        assert_eq!(
+            usage.take(&limit, now + Duration::seconds(10)),
+            Err("Request limit per minute of the provider is reached, try again later")
+        );
+        // One request every 20 seconds.
+        assert_eq!(usage.take(&limit, now + Duration::seconds(20)), Ok(()));
+        assert!(usage.take(&limit, now + Duration::seconds(30)).is_err());
+        assert_eq!(usage.day_count, 4);
+    }
+
+    #[test]
+    fn day_and_month_windows_reset() {
+        let limit = QuotaLimit {
+            per_minute: None,
+            per_day: Some(2),
+            per_month: Some(3),
+        };
+        let mut usage = Usage::default();
+        let day = time("2025-03-31T23:00:00Z");
+        assert_eq!(usage.take(&limit, day), Ok(()));
+        assert_eq!(usage.take(&limit, day), Ok(()));
+        assert_eq!(
+            usage.take(&limit, day),
+            Err("Daily request limit of the provider is reached")
+        );
+
+        let next_day = time("2025-04-01T00:30:00Z");
+        assert_eq!(usage.take(&limit, next_day), Ok(()));
+        assert_eq!(usage.month_count, 1);
+
+        let limit = QuotaLimit {
+            per_day: None,
+            ..limit
+        };
+        assert_eq!(usage.take(&limit, next_day), Ok(()));
+        assert_eq!(usage.take(&limit, next_day), Ok(()));
+        assert_eq!(
+            usage.take(&limit, next_day),
+            Err("Monthly request limit of the provider is reached")
+        );
+    }
+
+    #[test]
+    fn configured_limit_replaces_default() {
+        let mut configured = HashMap::new();
+        configured.insert(
+            "openweather".to_string(),
+            QuotaLimit {
+                per_day: Some(100),
+                ..QuotaLimit::default()
+            },
+        );
+        assert_eq!(
+            limits(&configured, WeatherService::OpenWeather.into()),
+            QuotaLimit {
+                per_minute: None,
+                per_day: Some(100),
+                per_month: None,
+            }
+        );
+        assert_eq!(
+            limits(&configured, WeatherService::WeatherApi.into()).per_month,
+            Some(1_000_000)
+        );
+        assert_eq!(
+            limits(&configured, WeatherService::MetNo.into()),
+            QuotaLimit::default()
+        );
+    }
+
+    #[test]
+    fn parallel_updates_keep_every_count() {
+        let dir = std::env::temp_dir().join(format!("weather-quota-{}", std::process::id()));
+        let path = dir.join("quota.json");
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..20 {
+                        update_ledger(&path, |ledger| {
+                            ledger
+                                .providers
+                                .entry("metno".to_string())
+                                .or_default()
+                                .day_count += 1;
+                            Ok::<(), ()>(())
+                        })
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(load_ledger(&path).providers["metno"].day_count, 160);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
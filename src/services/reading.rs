@@ -0,0 +1,81 @@
+use serde::Serialize;
+use std::fmt;
+
+/*
+Not every provider returns every value, and some values are computed here from others.
+Each such value of the forecast carries where it came from, so a missing value is shown
+as missing instead of a made up zero.
+*/
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Provenance {
+    /// Observed or reanalysed value of a past day.
+    Measured,
+    /// Value predicted by the provider.
+    Forecast,
+    /// Computed here from other values of the provider.
+    Derived,
+    Unavailable,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct Reading {
+    pub value: Option<f32>,
+    pub provenance: Provenance,
+}
+
+impl Reading {
+    pub const UNAVAILABLE: Reading = Reading {
+        value: None,
+        provenance: Provenance::Unavailable,
+    };
+
+    pub fn new(value: Option<f32>, provenance: Provenance) -> Self {
+        match value {
+            Some(value) => Self {
+                value: Some(value),
+                provenance,
+            },
+            None => Self::UNAVAILABLE,
+        }
+    }
+
+    pub fn derived(value: f32) -> Self {
+        Self::new(Some(value), Provenance::Derived)
+    }
+}
+
+/// Derived values are marked with `~`, missing ones are shown as `n/a`.
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match (self.value, self.provenance) {
+            (Some(value), Provenance::Derived) => format!("~{value}"),
+            (Some(value), _) => value.to_string(),
+            (None, _) => "n/a".to_string(),
+        };
+        f.pad(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Provenance, Reading};
+
+    #[test]
+    fn missing_value_is_unavailable() {
+        let reading = Reading::new(None, Provenance::Forecast);
+        assert_eq!(reading, Reading::UNAVAILABLE);
+        assert_eq!(reading.to_string(), "n/a");
+    }
+
+    #[test]
+    fn derived_value_is_marked() {
+        assert_eq!(Reading::derived(4.5).to_string(), "~4.5");
+        assert_eq!(format!("{:>6}", Reading::UNAVAILABLE), "   n/a");
+        assert_eq!(
+            Reading::new(Some(4.5), Provenance::Measured).to_string(),
+            "4.5"
+        );
+    }
+}
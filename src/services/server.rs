@@ -0,0 +1,261 @@
+use crate::services::weather_service::{
+    ForecastResponseData, ForecastTarget, Source, WeatherService,
+};
+use crate::utils::config;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Response, Server};
+
+/*
+Serves forecasts as JSON for other tools on the network, so only this process needs the API keys:
+
+GET /forecast?q=Kyiv,UA&date=tomorrow&provider=metno
+    Forecast like `weather get`, `date` is today and `provider` is the configured one by default.
+GET /providers
+    Known providers and the configured one.
+
+Errors are `{"error": "..."}` with 400 for a wrong query and 502 when the provider failed.
+Forecasts are kept in memory for the cache time, keyed by provider, address and the
+resolved date, so `tomorrow` and `+1d` share an entry.
+*/
+
+const MAX_ENTRIES: usize = 500;
+// Providers can be slow, other clients are answered by the other workers meanwhile.
+const WORKERS: usize = 8;
+
+type Failure = (u16, &'static str);
+
+struct ForecastCache {
+    ttl: Duration,
+    entries: HashMap<String, (Instant, String)>,
+}
+
+impl ForecastCache {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&String> {
+        match self.entries.get(key) {
+            Some((stored, body)) if stored.elapsed() < self.ttl => Some(body),
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, key: String, body: String) {
+        let ttl = self.ttl;
+        self.entries.retain(|_, (stored, _)| stored.elapsed() < ttl);
+        if self.entries.len() >= MAX_ENTRIES {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (stored, _))| *stored)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key, (Instant::now(), body));
+    }
+}
+
+pub fn weather_serve(bind: &str, ttl: Duration) {
+    let server = match Server::http(bind) {
+        Ok(server) => server,
+        Err(_) => return println!("Error: Can't listen on {bind}"),
+    };
+    println!("Serving forecasts on http://{bind}");
+    let server = Arc::new(server);
+    let cache = Arc::new(Mutex::new(ForecastCache::new(ttl)));
+
+    let workers: Vec<_> = (0..WORKERS)
+        .map(|_| {
+            let (server, cache) = (Arc::clone(&server), Arc::clone(&cache));
+            thread::spawn(move || {
+                while let Ok(request) = server.recv() {
+                    let (status, body) = match request.method() {
+                        Method::Get => respond(request.url(), &cache, fetch),
+                        _ => error(405, "Only GET is supported"),
+                    };
+                    let mut response = Response::from_string(body).with_status_code(status);
+                    if let Ok(header) = Header::from_bytes("Content-Type", "application/json") {
+                        response.add_header(header);
+                    }
+                    let _ = request.respond(response);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+fn fetch(
+    provider: WeatherService,
+    target: &ForecastTarget,
+) -> Result<ForecastResponseData, Failure> {
+    provider.forecast(target).map_err(|error| (502, error))
+}
+
+fn respond<F>(url: &str, cache: &Mutex<ForecastCache>, fetch: F) -> (u16, String)
+where
+    F: Fn(WeatherService, &ForecastTarget) -> Result<ForecastResponseData, Failure>,
+{
+    let url = match url::Url::parse(&format!("http://localhost{url}")) {
+        Ok(url) => url,
+        Err(_) => return error(400, "Incorrect request"),
+    };
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+    match url.path() {
+        "/providers" => {
+            let providers: Vec<_> = WeatherService::ALL
+                .iter()
+                .map(|provider| {
+                    json!({
+                        "name": provider.name(),
+                        "history": Source::from(*provider).history_strategy().is_some(),
+                    })
+                })
+                .collect();
+            let body = json!({
+                "default": config::get_provider().name(),
+                "providers": providers,
+            });
+            (200, body.to_string())
+        }
+        "/forecast" => {
+            let address = match query.get("q").map(|q| q.trim()) {
+                Some(address) if !address.is_empty() => address,
+                _ => return error(400, "Please set the address as q=Kyiv,UA"),
+            };
+            let date = query.get("date").map_or("today", |date| date.as_str());
+            let provider = match query.get("provider") {
+                Some(name) => match WeatherService::from(Some(name.as_str())) {
+                    provider if provider.has_forecast() => provider,
+                    _ => return error(400, "Provider unknown"),
+                },
+                None => config::get_provider(),
+            };
+
+            let target = match ForecastTarget::resolve(address, date, None) {
+                Ok(target) => target,
+                Err(message) => return error(400, message),
+            };
+
+            let key = format!(
+                "{}|{}|{}",
+                provider.name(),
+                address.to_lowercase(),
+                target.date_option.date.format("%Y-%m-%d")
+            );
+            if let Some(body) = cache.lock().ok().and_then(|cache| cache.get(&key).cloned()) {
+                return (200, body);
+            }
+            match fetch(provider, &target) {
+                Ok(forecast) => match serde_json::to_string(&forecast) {
+                    Ok(body) => {
+                        if let Ok(mut cache) = cache.lock() {
+                            cache.insert(key, body.clone());
+                        }
+                        (200, body)
+                    }
+                    Err(_) => error(500, "Can't serialize forecast"),
+                },
+                Err((status, message)) => error(status, message),
+            }
+        }
+        _ => error(404, "Not found, please use /forecast or /providers"),
+    }
+}
+
+fn error(status: u16, message: &str) -> (u16, String) {
+    (status, json!({ "error": message }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{respond, Failure, ForecastCache};
+    use crate::services::condition::Condition;
+    use crate::services::reading::Reading;
+    use crate::services::weather_service::{ForecastResponseData, ForecastTarget, WeatherService};
+    use std::cell::Cell;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    fn forecast(location: &str, date: &str) -> ForecastResponseData {
+        ForecastResponseData {
+            avg_temp: Reading::derived(8.0),
+            condition: Condition::Clear,
+            ..ForecastResponseData::sample(location, date, 4.0, 12.0)
+        }
+    }
+
+    #[test]
+    fn forecast_is_cached() {
+        let cache = Mutex::new(ForecastCache::new(Duration::from_secs(60)));
+        let calls = Cell::new(0);
+        let fetch = |provider: WeatherService, target: &ForecastTarget| {
+            calls.set(calls.get() + 1);
+            assert_eq!(provider, WeatherService::MetNo);
+            Ok(forecast(&target.address, &target.date))
+        };
+
+        let url = "/forecast?q=Kyiv%2C+UA&date=tomorrow&provider=metno";
+        let (status, body) = respond(url, &cache, fetch);
+        assert_eq!(status, 200);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["location"], "Kyiv, UA");
+        assert_eq!(json["date"], "tomorrow");
+
+        let url = "/forecast?q=kyiv,%20ua&date=Tomorrow&provider=metno";
+        assert_eq!(respond(url, &cache, fetch), (200, body.clone()));
+        let url = "/forecast?q=Kyiv,%20UA&date=%2B1d&provider=metno";
+        assert_eq!(respond(url, &cache, fetch), (200, body));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn expired_forecast_is_fetched_again() {
+        let cache = Mutex::new(ForecastCache::new(Duration::ZERO));
+        let calls = Cell::new(0);
+        let fetch = |_: WeatherService, target: &ForecastTarget| {
+            calls.set(calls.get() + 1);
+            Ok(forecast(&target.address, &target.date))
+        };
+        respond("/forecast?q=Kyiv&provider=metno", &cache, fetch);
+        respond("/forecast?q=Kyiv&provider=metno", &cache, fetch);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn errors_are_json() {
+        let cache = Mutex::new(ForecastCache::new(Duration::from_secs(60)));
+        let fetch = |_: WeatherService, _: &ForecastTarget| -> Result<_, Failure> {
+            Err((502, "Provider is down"))
+        };
+
+        let (status, body) = respond("/forecast?q=Kyiv&provider=metno", &cache, fetch);
+        assert_eq!(status, 502);
+        assert_eq!(body, r#"{"error":"Provider is down"}"#);
+        assert!(cache.lock().unwrap().entries.is_empty());
+
+        assert_eq!(respond("/forecast?q=+", &cache, fetch).0, 400);
+        assert_eq!(
+            respond("/forecast?q=Kyiv&provider=acme", &cache, fetch).0,
+            400
+        );
+        assert_eq!(
+            respond("/forecast?q=Kyiv&date=someday", &cache, fetch).0,
+            400
+        );
+        assert_eq!(respond("/weather", &cache, fetch).0, 404);
+    }
+}
@@ -0,0 +1,354 @@
+use crate::services::weather_service::{ForecastResponseData, ForecastTarget, WeatherService};
+use crate::utils::config;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Tabs};
+use ratatui::Frame;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/*
+`weather tui` keeps forecasts of the configured addresses on screen:
+
+┌Addresses───┐┌Providers──────────────────────────────────────┐
+│> Kyiv, UA  ││ openweather │ weatherapi │ metno              │
+│  Lviv, UA  │└───────────────────────────────────────────────┘
+│            │┌Today (daily) in Kyiv, UA──────────────────────┐
+│            ││ (~~)/ Light rain, 4..9 °C, humidity 80 %      │
+│            │└───────────────────────────────────────────────┘
+│            │┌Mon 03────┐┌Tue 04────┐┌Wed 05────┐ ...
+└────────────┘└──────────┘└──────────┘└──────────┘
+ ↑↓ address  ←→ provider  r refresh  q quit
+
+Forecasts are fetched in the background, all days of an address from one request, and kept
+by address and provider, so switching back shows them at once. All addresses are fetched again
+with the selected provider every --refresh.
+*/
+
+const DAYS: i64 = 5;
+const TICK: Duration = Duration::from_millis(250);
+
+type Key = (usize, WeatherService);
+/// Forecasts of the next days, or why the address can't be forecasted.
+type Fetched = Result<Vec<ForecastResponseData>, &'static str>;
+
+struct Dashboard {
+    addresses: Vec<String>,
+    selected: usize,
+    provider: usize,
+    forecasts: HashMap<Key, Fetched>,
+    loading: HashSet<Key>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Action {
+    Quit,
+    Fetch(Vec<Key>),
+    Nothing,
+}
+
+impl Dashboard {
+    fn new(addresses: Vec<String>, provider: WeatherService) -> Self {
+        Dashboard {
+            addresses,
+            selected: 0,
+            provider: WeatherService::ALL
+                .iter()
+                .position(|service| *service == provider)
+                .unwrap_or(0),
+            forecasts: HashMap::new(),
+            loading: HashSet::new(),
+        }
+    }
+
+    fn provider(&self) -> WeatherService {
+        WeatherService::ALL[self.provider]
+    }
+
+    fn key(&self) -> Key {
+        (self.selected, self.provider())
+    }
+
+    fn handle(&mut self, code: KeyCode) -> Action {
+        let count = self.addresses.len();
+        let providers = WeatherService::ALL.len();
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Char('r') => return Action::Fetch(self.all()),
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1) % count,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => {
+                self.provider = (self.provider + 1) % providers
+            }
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::BackTab => {
+                self.provider = (self.provider + providers - 1) % providers
+            }
+            _ => return Action::Nothing,
+        }
+        match self.forecasts.contains_key(&self.key()) {
+            true => Action::Nothing,
+            false => Action::Fetch(vec![self.key()]),
+        }
+    }
+
+    /// Every address with the selected provider.
+    fn all(&self) -> Vec<Key> {
+        (0..self.addresses.len())
+            .map(|index| (index, self.provider()))
+            .collect()
+    }
+
+    fn fetch(&mut self, keys: Vec<Key>, sender: &Sender<(Key, Fetched)>) {
+        for key in keys {
+            if !self.loading.insert(key) {
+                continue;
+            }
+            let address = self.addresses[key.0].clone();
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let _ = sender.send((key, fetch(&address, key.1)));
+            });
+        }
+    }
+
+    fn loaded(&mut self, key: Key, fetched: Fetched) {
+        self.loading.remove(&key);
+        self.forecasts.insert(key, fetched);
+    }
+}
+
+fn fetch(address: &str, provider: WeatherService) -> Fetched {
+    let last = ForecastTarget::resolve(address, &format!("+{}d", DAYS - 1), None)?;
+    provider.forecast_days(&last)
+}
+
+pub fn weather_tui(refresh: Duration) {
+    let addresses = config::load().addresses;
+    if addresses.is_empty() {
+        return println!("Error: Please add addresses to the config to show them");
+    }
+    let mut dashboard = Dashboard::new(addresses, config::get_provider());
+    let (sender, receiver) = mpsc::channel();
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut dashboard, refresh, &sender, &receiver);
+    ratatui::restore();
+    if result.is_err() {
+        println!("Error: Can't draw on the terminal");
+    }
+}
+
+fn run(
+    terminal: &mut ratatui::DefaultTerminal,
+    dashboard: &mut Dashboard,
+    refresh: Duration,
+    sender: &Sender<(Key, Fetched)>,
+    receiver: &Receiver<(Key, Fetched)>,
+) -> std::io::Result<()> {
+    dashboard.fetch(dashboard.all(), sender);
+    let mut refreshed = Instant::now();
+    loop {
+        terminal.draw(|frame| draw(frame, dashboard))?;
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    match dashboard.handle(key.code) {
+                        Action::Quit => return Ok(()),
+                        Action::Fetch(keys) => dashboard.fetch(keys, sender),
+                        Action::Nothing => {}
+                    }
+                }
+            }
+        }
+        while let Ok((key, fetched)) = receiver.try_recv() {
+            dashboard.loaded(key, fetched);
+        }
+        if refreshed.elapsed() >= refresh {
+            dashboard.fetch(dashboard.all(), sender);
+            refreshed = Instant::now();
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, dashboard: &Dashboard) {
+    let [main, footer] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let [sidebar, content] =
+        Layout::horizontal([Constraint::Length(24), Constraint::Min(0)]).areas(main);
+    let [providers, today, days] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(6),
+        Constraint::Min(0),
+    ])
+    .areas(content);
+
+    let items: Vec<ListItem> = dashboard
+        .addresses
+        .iter()
+        .map(|address| ListItem::new(address.as_str()))
+        .collect();
+    let mut state = ListState::default().with_selected(Some(dashboard.selected));
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(Block::bordered().title("Addresses"))
+            .highlight_symbol("> ")
+            .highlight_style(Style::new().add_modifier(Modifier::BOLD)),
+        sidebar,
+        &mut state,
+    );
+
+    let names: Vec<&str> = WeatherService::ALL
+        .iter()
+        .map(|provider| provider.name())
+        .collect();
+    frame.render_widget(
+        Tabs::new(names)
+            .select(dashboard.provider)
+            .block(Block::bordered().title("Providers"))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+        providers,
+    );
+
+    let address = &dashboard.addresses[dashboard.selected];
+    // Providers give days, not the current weather.
+    let today_block = Block::bordered().title(format!("Today (daily) in {address}"));
+    match dashboard.forecasts.get(&dashboard.key()) {
+        Some(Ok(forecasts)) => {
+            let lines = match forecasts.first() {
+                Some(forecast) => today_lines(forecast),
+                None => Vec::new(),
+            };
+            frame.render_widget(Paragraph::new(lines).block(today_block), today);
+            draw_days(frame, days, forecasts);
+        }
+        Some(Err(error)) => {
+            frame.render_widget(Paragraph::new(*error).block(today_block), today);
+        }
+        None => frame.render_widget(Paragraph::new("Loading...").block(today_block), today),
+    }
+
+    let status = match dashboard.loading.contains(&dashboard.key()) {
+        true => "  updating...",
+        false => "",
+    };
+    frame.render_widget(
+        Paragraph::new(format!(
+            " ↑↓ address  ←→ provider  r refresh  q quit{status}"
+        )),
+        footer,
+    );
+}
+
+fn today_lines(forecast: &ForecastResponseData) -> Vec<Line<'static>> {
+    let condition = match &forecast.condition_text {
+        Some(text) => text.clone(),
+        None => forecast.condition.to_string(),
+    };
+    vec![
+        Line::from(format!(
+            "{} {condition}, {}..{} °C, average {} °C",
+            forecast.condition.ascii(),
+            forecast.min_temp,
+            forecast.max_temp,
+            forecast.avg_temp
+        )),
+        Line::from(format!(
+            "Humidity {} %, wind up to {} km/h, gusts {} km/h",
+            forecast.avghumidity, forecast.maxwind_kph, forecast.gust_kph
+        )),
+        Line::from(format!(
+            "Precipitation {} mm, chance {} %, pressure {} mb",
+            forecast.precip_mm, forecast.precip_chance, forecast.pressure_mb
+        )),
+        Line::from(format!(
+            "UV {}, clouds {} %, visibility {} km",
+            forecast.uv_index, forecast.cloud_cover, forecast.visibility_km
+        )),
+    ]
+}
+
+fn draw_days(frame: &mut Frame, area: Rect, forecasts: &[ForecastResponseData]) {
+    let columns =
+        Layout::horizontal(vec![Constraint::Ratio(1, DAYS as u32); forecasts.len()]).split(area);
+    for (forecast, column) in forecasts.iter().zip(columns.iter()) {
+        let lines = vec![
+            Line::from(forecast.condition.ascii()),
+            Line::from(forecast.condition.to_string()),
+            Line::from(format!("{}..{} °C", forecast.min_temp, forecast.max_temp)),
+            Line::from(format!("rain {} %", forecast.precip_chance)),
+            Line::from(format!("wind {} km/h", forecast.maxwind_kph)),
+        ];
+        frame.render_widget(
+            Paragraph::new(lines)
+                .wrap(ratatui::widgets::Wrap { trim: true })
+                .block(Block::bordered().title(forecast.date.clone())),
+            *column,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{draw, Action, Dashboard};
+    use crate::services::weather_service::WeatherService;
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::KeyCode;
+    use ratatui::Terminal;
+
+    fn dashboard() -> Dashboard {
+        Dashboard::new(
+            vec!["Kyiv, UA".to_string(), "Lviv, UA".to_string()],
+            WeatherService::MetNo,
+        )
+    }
+
+    #[test]
+    fn keys_move_and_fetch_what_is_missing() {
+        let mut dashboard = dashboard();
+        assert_eq!(dashboard.provider(), WeatherService::MetNo);
+        dashboard.loaded((1, WeatherService::MetNo), Err("No location found"));
+
+        assert_eq!(dashboard.handle(KeyCode::Down), Action::Nothing);
+        assert_eq!(
+            dashboard.handle(KeyCode::Down),
+            Action::Fetch(vec![(0, WeatherService::MetNo)])
+        );
+        assert_eq!(
+            dashboard.handle(KeyCode::Left),
+            Action::Fetch(vec![(0, WeatherService::WeatherApi)])
+        );
+        dashboard.handle(KeyCode::Right);
+        dashboard.handle(KeyCode::Right);
+        assert_eq!(dashboard.provider(), WeatherService::OpenWeather);
+        assert_eq!(
+            dashboard.handle(KeyCode::Char('r')),
+            Action::Fetch(vec![
+                (0, WeatherService::OpenWeather),
+                (1, WeatherService::OpenWeather)
+            ])
+        );
+        assert_eq!(dashboard.handle(KeyCode::Char('q')), Action::Quit);
+    }
+
+    #[test]
+    fn draws_addresses_providers_and_errors() {
+        let mut dashboard = dashboard();
+        dashboard.loaded((0, WeatherService::MetNo), Err("No location found"));
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+        terminal.draw(|frame| draw(frame, &dashboard)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("> Kyiv, UA"));
+        assert!(screen.contains("weatherapi"));
+        assert!(screen.contains("Today (daily) in Kyiv, UA"));
+        assert!(screen.contains("No location found"));
+    }
+}
@@ -0,0 +1,454 @@
+use crate::services::comfort::Comfort;
+use crate::services::condition::Condition;
+use crate::services::executor;
+use crate::services::reading::{Provenance, Reading};
+use crate::services::weather_service::{ForecastResponseData, ForecastTarget};
+use crate::utils::config;
+use crate::utils::meteo;
+use crate::utils::output::{csv_field, OutputFormat};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/*
+Rules are kept in the config next to the addresses they are checked for:
+
+addresses = ["Kyiv, UA"]
+
+[[rules]]
+name = "frost"
+severity = "warning"
+when = "min_temp < 0"
+
+[[rules]]
+name = "storm"
+severity = "critical"
+when = "wind > 50 km/h"
+
+Rule is `<field> <operator> <value> [unit]`, or `condition contains <condition>`.
+A value the provider doesn't return never triggers a rule.
+*/
+
+pub const DEFAULT_DAYS: i64 = 3;
+const EXIT_TRIGGERED: i32 = 1;
+const EXIT_NOT_CHECKED: i32 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Rule {
+    pub name: String,
+    #[serde(default)]
+    pub severity: Severity,
+    pub when: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    MinTemp,
+    MaxTemp,
+    AvgTemp,
+    FeelsLike,
+    Wind,
+    Gust,
+    Humidity,
+    Precip,
+    PrecipChance,
+    Snow,
+    Pressure,
+    Uv,
+    Visibility,
+    CloudCover,
+    DewPoint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Check {
+    Value(Field, Operator, f32),
+    ConditionIs(Condition),
+    ConditionText(String),
+}
+
+#[derive(Serialize)]
+pub struct Alert {
+    pub rule: String,
+    pub severity: Severity,
+    pub location: String,
+    pub date: String,
+    pub value: String,
+}
+
+#[derive(Serialize)]
+struct WatchReport<'a> {
+    alerts: &'a [Alert],
+    errors: &'a [String],
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        let field = match name {
+            "min_temp" => Field::MinTemp,
+            "max_temp" => Field::MaxTemp,
+            "avg_temp" | "temp" => Field::AvgTemp,
+            "feels_like" => Field::FeelsLike,
+            "wind" | "maxwind_kph" => Field::Wind,
+            "gust" | "gust_kph" => Field::Gust,
+            "humidity" | "avghumidity" => Field::Humidity,
+            "precip" | "precip_mm" => Field::Precip,
+            "precip_chance" => Field::PrecipChance,
+            "snow" | "snow_cm" => Field::Snow,
+            "pressure" | "pressure_mb" => Field::Pressure,
+            "uv" | "uv_index" => Field::Uv,
+            "visibility" | "visibility_km" => Field::Visibility,
+            "cloud_cover" | "clouds" => Field::CloudCover,
+            "dew_point" | "dew_point_c" => Field::DewPoint,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    fn reading(&self, forecast: &ForecastResponseData) -> Reading {
+        match self {
+            Field::MinTemp => Reading::new(Some(forecast.min_temp), Provenance::Forecast),
+            Field::MaxTemp => Reading::new(Some(forecast.max_temp), Provenance::Forecast),
+            Field::AvgTemp => forecast.avg_temp,
+            Field::FeelsLike => Comfort::new(forecast).feels_like,
+            Field::Wind => forecast.maxwind_kph,
+            Field::Gust => forecast.gust_kph,
+            Field::Humidity => forecast.avghumidity,
+            Field::Precip => forecast.precip_mm,
+            Field::PrecipChance => forecast.precip_chance,
+            Field::Snow => forecast.snow_cm,
+            Field::Pressure => forecast.pressure_mb,
+            Field::Uv => forecast.uv_index,
+            Field::Visibility => forecast.visibility_km,
+            Field::CloudCover => forecast.cloud_cover,
+            Field::DewPoint => forecast.dew_point_c,
+        }
+    }
+}
+
+impl Operator {
+    fn apply(&self, left: f32, right: f32) -> bool {
+        match self {
+            Operator::Less => left < right,
+            Operator::LessOrEqual => left <= right,
+            Operator::Greater => left > right,
+            Operator::GreaterOrEqual => left >= right,
+            Operator::Equal => left == right,
+            Operator::NotEqual => left != right,
+        }
+    }
+}
+
+fn rule_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"^\s*(?P<field>\w+)\s*(?P<op><=|>=|==|!=|<|>|=|\bcontains\b|\bis\b)\s*(?P<value>.+?)\s*$",
+        )
+        .expect("valid regex")
+    })
+}
+
+impl FromStr for Check {
+    type Err = &'static str;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let captures = rule_pattern()
+            .captures(rule)
+            .ok_or("Rule should look like `min_temp < 0` or `condition contains snow`")?;
+        let field = captures["field"].to_lowercase();
+        let value = captures["value"].trim().to_lowercase();
+
+        match (field.as_str(), &captures["op"]) {
+            ("condition", "contains" | "is" | "=" | "==") => {
+                Ok(match Condition::from_str(&value) {
+                    Ok(condition) => Check::ConditionIs(condition),
+                    Err(_) => Check::ConditionText(value),
+                })
+            }
+            ("condition", _) => Err("Condition can be checked only with `contains`"),
+            (field, op) => {
+                let field = Field::from_name(field).ok_or("Unknown field in rule")?;
+                let operator = match op {
+                    "<" => Operator::Less,
+                    "<=" => Operator::LessOrEqual,
+                    ">" => Operator::Greater,
+                    ">=" => Operator::GreaterOrEqual,
+                    "=" | "==" => Operator::Equal,
+                    "!=" => Operator::NotEqual,
+                    _ => return Err("Only a condition can be checked with `contains`"),
+                };
+                Ok(Check::Value(field, operator, parse_value(&value)?))
+            }
+        }
+    }
+}
+
+// Values are in the units of the forecast (°C, Km/h, mm, cm, %, hPa, km), m/s is converted.
+fn parse_value(value: &str) -> Result<f32, &'static str> {
+    let number_end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(value.len());
+    let number = value[..number_end]
+        .parse::<f32>()
+        .map_err(|_| "Rule value should be a number")?;
+    match value[number_end..].trim() {
+        "m/s" => Ok(number * meteo::MPS_TO_KPH),
+        "" | "c" | "°c" | "km/h" | "kph" | "mm" | "cm" | "%" | "hpa" | "mb" | "km" => Ok(number),
+        _ => Err("Unknown unit in rule"),
+    }
+}
+
+impl Check {
+    /// Value which triggered the check.
+    fn triggered(&self, forecast: &ForecastResponseData) -> Option<String> {
+        match self {
+            Check::Value(field, operator, threshold) => {
+                let reading = field.reading(forecast);
+                reading
+                    .value
+                    .filter(|value| operator.apply(*value, *threshold))
+                    .map(|_| reading.to_string())
+            }
+            Check::ConditionIs(condition) => {
+                if forecast.condition.matches(condition) {
+                    Some(forecast.condition.to_string())
+                } else {
+                    None
+                }
+            }
+            Check::ConditionText(text) => forecast
+                .condition_text
+                .as_ref()
+                .filter(|condition_text| condition_text.to_lowercase().contains(text.as_str()))
+                .cloned(),
+        }
+    }
+}
+
+fn evaluate(rules: &[(Rule, Check)], forecast: &ForecastResponseData) -> Vec<Alert> {
+    rules
+        .iter()
+        .filter_map(|(rule, check)| {
+            check.triggered(forecast).map(|value| Alert {
+                rule: rule.name.clone(),
+                severity: rule.severity,
+                location: forecast.location.clone(),
+                date: forecast.date.clone(),
+                value,
+            })
+        })
+        .collect()
+}
+
+fn parse_rules(rules: &[Rule]) -> Result<Vec<(Rule, Check)>, String> {
+    rules
+        .iter()
+        .map(|rule| match Check::from_str(&rule.when) {
+            Ok(check) => Ok((rule.clone(), check)),
+            Err(error) => Err(format!("Rule {}: {error}", rule.name)),
+        })
+        .collect()
+}
+
+/// Exit code for cron: 1 when any rule fired, 2 when nothing could be checked.
+pub fn weather_watch(days: i64, format: OutputFormat) -> i32 {
+    let config = config::load();
+    if config.addresses.is_empty() || config.rules.is_empty() {
+        println!("Error: Please add addresses and rules to the config to watch them");
+        return EXIT_NOT_CHECKED;
+    }
+    let rules = match parse_rules(&config.rules) {
+        Ok(rules) => rules,
+        Err(error) => {
+            println!("Error: {error}");
+            return EXIT_NOT_CHECKED;
+        }
+    };
+    let provider = config::get_provider();
+
+    let mut alerts = Vec::new();
+    let mut errors = Vec::new();
+    let mut checked = 0;
+    let mut jobs = Vec::new();
+    let mut labels = Vec::new();
+    let last = format!("+{}d", days - 1);
+    for address in &config.addresses {
+        match ForecastTarget::resolve(address, &last, None) {
+            Ok(target) => {
+                jobs.push((provider, target));
+                labels.push(address);
+            }
+            Err(error) => errors.push(format!("{address}: {error}")),
+        }
+    }
+    for (label, forecasts) in labels.iter().zip(executor::forecast_days_all(jobs)) {
+        match forecasts {
+            Ok(forecasts) => {
+                for forecast in &forecasts {
+                    checked += 1;
+                    alerts.extend(evaluate(&rules, forecast));
+                }
+            }
+            Err(error) => errors.push(format!("{label}: {error}")),
+        }
+    }
+
+    render_alerts(&alerts, &errors, format);
+    match (alerts.is_empty(), checked) {
+        (false, _) => EXIT_TRIGGERED,
+        (true, 0) => EXIT_NOT_CHECKED,
+        (true, _) => 0,
+    }
+}
+
+fn render_alerts(alerts: &[Alert], errors: &[String], format: OutputFormat) {
+    match format {
+        OutputFormat::Table => {
+            for alert in alerts {
+                println!(
+                    "[{}] {}: {} on {} ({})",
+                    alert.severity.name(),
+                    alert.rule,
+                    alert.location,
+                    alert.date,
+                    alert.value
+                );
+            }
+            if alerts.is_empty() {
+                println!("No rules triggered");
+            }
+            errors.iter().for_each(|error| println!("Error: {error}"));
+        }
+        OutputFormat::Csv => {
+            println!("rule,severity,location,date,value");
+            for alert in alerts {
+                println!(
+                    "{},{},{},{},{}",
+                    csv_field(&alert.rule),
+                    alert.severity.name(),
+                    csv_field(&alert.location),
+                    alert.date,
+                    csv_field(&alert.value)
+                );
+            }
+            errors.iter().for_each(|error| eprintln!("Error: {error}"));
+        }
+        OutputFormat::Json => match serde_json::to_string_pretty(&WatchReport { alerts, errors }) {
+            Ok(json) => println!("{json}"),
+            Err(_) => println!("Error: Can't serialize alerts"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, parse_rules, Check, Field, Operator, Rule, Severity};
+    use crate::services::condition::Condition;
+    use crate::services::reading::{Provenance, Reading};
+    use crate::services::weather_service::ForecastResponseData;
+    use std::str::FromStr;
+
+    #[test]
+    fn parses_rules() {
+        assert_eq!(
+            Check::from_str("min_temp < 0"),
+            Ok(Check::Value(Field::MinTemp, Operator::Less, 0.0))
+        );
+        assert_eq!(
+            Check::from_str("wind > 50 km/h"),
+            Ok(Check::Value(Field::Wind, Operator::Greater, 50.0))
+        );
+        assert_eq!(
+            Check::from_str("gust >= 10 m/s"),
+            Ok(Check::Value(Field::Gust, Operator::GreaterOrEqual, 36.0))
+        );
+        assert_eq!(
+            Check::from_str("condition contains snow"),
+            Ok(Check::ConditionIs(Condition::Snow))
+        );
+        assert_eq!(
+            Check::from_str("condition contains blizzard"),
+            Ok(Check::ConditionText("blizzard".to_string()))
+        );
+        assert!(Check::from_str("pressure ~ 1000").is_err());
+        assert!(Check::from_str("moon > 1").is_err());
+        assert!(Check::from_str("wind > 50 knots").is_err());
+        assert!(Check::from_str("wind contains 50").is_err());
+    }
+
+    #[test]
+    fn evaluates_rules() {
+        let rules = parse_rules(&[
+            rule("frost", Severity::Warning, "min_temp < 0"),
+            rule("storm", Severity::Critical, "wind > 50 km/h"),
+            rule("snow", Severity::Info, "condition contains snow"),
+            rule("gusts", Severity::Critical, "gust > 60"),
+        ])
+        .unwrap();
+        let alerts = evaluate(&rules, &forecast());
+
+        let fired: Vec<&str> = alerts.iter().map(|alert| alert.rule.as_str()).collect();
+        assert_eq!(fired, vec!["frost", "snow"]);
+        assert_eq!(alerts[0].value, "-3");
+        assert_eq!(alerts[1].value, "heavy snow");
+    }
+
+    #[test]
+    fn reports_invalid_rule() {
+        let error = parse_rules(&[rule("bad", Severity::Info, "wind >")]).unwrap_err();
+        assert!(error.starts_with("Rule bad:"));
+    }
+
+    fn rule(name: &str, severity: Severity, when: &str) -> Rule {
+        Rule {
+            name: name.to_string(),
+            severity,
+            when: when.to_string(),
+        }
+    }
+
+    fn forecast() -> ForecastResponseData {
+        let forecast = |value: f32| Reading::new(Some(value), Provenance::Forecast);
+        ForecastResponseData {
+            avg_temp: forecast(-1.0),
+            maxwind_kph: forecast(20.0),
+            avghumidity: forecast(90.0),
+            condition: Condition::HeavySnow,
+            condition_text: Some("Heavy snow".to_string()),
+            precip_mm: forecast(8.0),
+            snow_cm: forecast(9.0),
+            ..ForecastResponseData::sample("Kyiv, UA", "2025-01-05", -3.0, 1.0)
+        }
+    }
+}
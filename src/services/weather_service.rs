@@ -1,11 +1,30 @@
 use crate::services::{
-    open_weather_strategy::OpenWeatherStrategy, weatherapi_strategy::WeatherApiStrategy,
+    comfort::Comfort,
+    condition::Condition,
+    executor::{self, ErrorKind, ForecastError},
+    geocoding::{self, Place},
+    metno_strategy::{self, MetNoStrategy},
+    normals::{self, Anomaly, Normals},
+    notify,
+    open_weather_strategy::{self, OpenWeatherStrategy},
+    quota,
+    reading::Reading,
+    weatherapi_strategy::{self, WeatherApiStrategy},
 };
 use crate::utils::config;
+use crate::utils::date::{DateOption, Zone};
+use crate::utils::http::{self, HttpError};
+use crate::utils::meteo;
+use crate::utils::output::{self, Detail};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::future::Future;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum WeatherService {
     OpenWeather,
     WeatherApi,
+    MetNo,
     // AccuWeather,
     // AerisWeather,
     UnknownService,
@@ -18,6 +37,7 @@ impl From<Option<&str>> for WeatherService {
             Some(provider) => match provider.to_lowercase().as_str() {
                 "openweather" => WeatherService::OpenWeather,
                 "weatherapi" => WeatherService::WeatherApi,
+                "metno" => WeatherService::MetNo,
                 // "accuweather" => WeatherService::AccuWeather,
                 // "aerisweather" => WeatherService::AerisWeather,
                 _ => WeatherService::UnknownService,
@@ -27,34 +47,331 @@ impl From<Option<&str>> for WeatherService {
     }
 }
 
-fn render_forecast_data(response_data: Result<ForecastResponseData, &'static str>) {
+impl WeatherService {
+    /// Providers of forecasts, Open-Meteo is only a `Source` of history and normals.
+    pub const ALL: [WeatherService; 3] = [
+        WeatherService::OpenWeather,
+        WeatherService::WeatherApi,
+        WeatherService::MetNo,
+    ];
+
+    pub fn has_forecast(&self) -> bool {
+        Self::ALL.contains(self)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeatherService::OpenWeather => "openweather",
+            WeatherService::WeatherApi => "weatherapi",
+            WeatherService::MetNo => "metno",
+            WeatherService::UnknownService => "unknown",
+            WeatherService::MissingService => "missing",
+        }
+    }
+
+    /// Key given at build time, `None` for providers without keys.
+    pub fn api_key(&self) -> Option<&'static str> {
+        match self {
+            WeatherService::OpenWeather => Some(open_weather_strategy::API_KEY),
+            WeatherService::WeatherApi => Some(weatherapi_strategy::API_KEY),
+            _ => None,
+        }
+    }
+
+    pub fn base_url(&self) -> Option<&'static str> {
+        match self {
+            WeatherService::OpenWeather => Some(open_weather_strategy::URL),
+            WeatherService::WeatherApi => Some(weatherapi_strategy::URL),
+            WeatherService::MetNo => Some(metno_strategy::URL),
+            _ => None,
+        }
+    }
+
+    /// Forecast within the provider's limits, see `executor`.
+    pub fn forecast(&self, target: &ForecastTarget) -> Result<ForecastResponseData, &'static str> {
+        executor::forecast(*self, target)
+    }
+
+    /// Every day from today to the target's date out of one request, see `executor`.
+    pub fn forecast_days(
+        &self,
+        target: &ForecastTarget,
+    ) -> Result<Vec<ForecastResponseData>, &'static str> {
+        executor::forecast_days(*self, target)
+    }
+
+    pub async fn forecast_days_async(
+        &self,
+        target: &ForecastTarget,
+    ) -> Result<Vec<ForecastResponseData>, ForecastError> {
+        match self {
+            WeatherService::OpenWeather => {
+                WeatherForecastData::new(OpenWeatherStrategy)
+                    .get_days(target)
+                    .await
+            }
+            WeatherService::WeatherApi => {
+                WeatherForecastData::new(WeatherApiStrategy)
+                    .get_days(target)
+                    .await
+            }
+            WeatherService::MetNo => {
+                WeatherForecastData::new(MetNoStrategy)
+                    .get_days(target)
+                    .await
+            }
+            _ => {
+                WeatherForecastData::new(WeatherApiStrategy)
+                    .get_days(target)
+                    .await
+            }
+        }
+    }
+
+    pub async fn forecast_async(
+        &self,
+        target: &ForecastTarget,
+    ) -> Result<ForecastResponseData, ForecastError> {
+        match self {
+            WeatherService::OpenWeather => {
+                WeatherForecastData::new(OpenWeatherStrategy)
+                    .get_forecast(target)
+                    .await
+            }
+            WeatherService::WeatherApi => {
+                WeatherForecastData::new(WeatherApiStrategy)
+                    .get_forecast(target)
+                    .await
+            }
+            WeatherService::MetNo => {
+                WeatherForecastData::new(MetNoStrategy)
+                    .get_forecast(target)
+                    .await
+            }
+            _ => {
+                WeatherForecastData::new(WeatherApiStrategy)
+                    .get_forecast(target)
+                    .await
+            }
+        }
+    }
+}
+
+/// Service asked within the quota and the limits: a forecast provider, or Open-Meteo which has
+/// only history and normals and so can't be given where a forecast provider is expected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Source {
+    Forecast(WeatherService),
+    OpenMeteo,
+}
+
+impl From<WeatherService> for Source {
+    fn from(provider: WeatherService) -> Source {
+        Source::Forecast(provider)
+    }
+}
+
+impl From<Option<&str>> for Source {
+    fn from(source: Option<&str>) -> Source {
+        match source {
+            Some(name) if name.eq_ignore_ascii_case("openmeteo") => Source::OpenMeteo,
+            source => Source::Forecast(WeatherService::from(source)),
+        }
+    }
+}
+
+impl Source {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::Forecast(provider) => provider.name(),
+            Source::OpenMeteo => "openmeteo",
+        }
+    }
+}
+
+/// Address with date resolved in the location's time zone.
+/// "today" in Los Angeles may still be yesterday in Kyiv. Until the zone is known the date is resolved
+/// on this machine, providers asked by address give the zone in their response, providers asked
+/// by coordinates get it from geocoding, and the date is resolved again in it.
+#[derive(Clone)]
+pub struct ForecastTarget {
+    pub address: String,
+    /// Date as it was given.
+    pub date: String,
+    pub date_option: DateOption,
+    pub place: Option<Place>,
+    pub timezone: Option<Zone>,
+}
+
+impl ForecastTarget {
+    pub fn resolve(
+        address: &str,
+        date: &str,
+        timezone: Option<Zone>,
+    ) -> Result<Self, &'static str> {
+        Ok(Self {
+            address: address.to_string(),
+            date: date.to_string(),
+            date_option: DateOption::new(date, timezone)?,
+            place: None,
+            timezone,
+        })
+    }
+
+    /// Same address for another date, without geocoding it again.
+    pub fn on_date(&self, date: &str) -> Result<Self, &'static str> {
+        let zone = self
+            .timezone
+            .or(self.place.as_ref().map(|place| Zone::from(place.timezone)));
+        Ok(Self {
+            date: date.to_string(),
+            date_option: DateOption::new(date, zone)?,
+            ..self.clone()
+        })
+    }
+
+    /// Date in the location's `zone`, unless another one was asked for.
+    pub fn date_in(&self, zone: Zone) -> Result<DateOption, &'static str> {
+        DateOption::new(&self.date, Some(self.timezone.unwrap_or(zone)))
+    }
+}
+
+fn render_forecast_data(
+    response_data: Result<ForecastResponseData, &'static str>,
+    options: &ForecastOptions,
+) {
     match response_data {
-        Ok(response_data) => response_data.render(),
+        Ok(response_data) => response_data.render(options),
         Err(error) => println!("Error: {error}"),
     };
 }
 
-pub fn weather_forecast(address: &str, date: &str) {
-    let response_data = match config::get_provider() {
-        WeatherService::OpenWeather => {
-            WeatherForecastData::new(OpenWeatherStrategy).get_forecast(address, date)
+#[derive(Default)]
+pub struct ForecastOptions {
+    pub only: Option<Condition>,
+    pub tz: Option<Zone>,
+    pub normals: bool,
+    pub detail: Detail,
+    pub comfort: bool,
+    /// Name of the configured sink which gets the forecast too.
+    pub notify: Option<String>,
+}
+
+pub fn weather_forecast(address: &str, date: &str, options: &ForecastOptions) {
+    let response_data = ForecastTarget::resolve(address, date, options.tz).and_then(|target| {
+        let mut forecast = config::get_provider().forecast(&target)?;
+        if options.normals {
+            forecast.normal = forecast_anomaly(&target, &forecast);
         }
-        WeatherService::WeatherApi => {
-            WeatherForecastData::new(WeatherApiStrategy).get_forecast(address, date)
+        Ok(forecast)
+    });
+    match (response_data, options.only) {
+        (Ok(forecast), Some(filter)) if !forecast.condition.matches(&filter) => println!(
+            "No {filter} expected in {} on date {} ({})",
+            forecast.location, forecast.date, forecast.condition
+        ),
+        (Ok(forecast), _) => {
+            forecast.render(options);
+            if let Some(sink) = &options.notify {
+                match notify::notify(sink, &forecast) {
+                    Ok(()) => println!("Sent to {sink}"),
+                    Err(error) => println!("Error: {error}"),
+                }
+            }
         }
-        _ => WeatherForecastData::new(WeatherApiStrategy).get_forecast(address, date),
+        (response_data, _) => render_forecast_data(response_data, options),
+    }
+}
+
+// Forecast is still shown when normals can't be built, only with a warning.
+fn forecast_anomaly(target: &ForecastTarget, forecast: &ForecastResponseData) -> Option<Anomaly> {
+    let place = match &target.place {
+        Some(place) => place.clone(),
+        None => match geocoding::geocode(&target.address) {
+            Ok(place) => place,
+            Err(error) => {
+                println!("Normals aren't available: {error}");
+                return None;
+            }
+        },
     };
+    let date = NaiveDate::parse_from_str(&forecast.date, "%Y-%m-%d").ok()?;
+    match Normals::load(&place, normals::DEFAULT_YEARS) {
+        Ok(normals) => forecast
+            .avg_temp
+            .value
+            .and_then(|avg_temp| normals.anomaly(date, avg_temp)),
+        Err(error) => {
+            println!("Normals aren't available: {error}");
+            None
+        }
+    }
+}
 
-    render_forecast_data(response_data);
+pub struct ForecastQuery {
+    pub url: String,
+    pub date_option: DateOption,
+    pub place: Option<Place>,
 }
 
 pub trait ForecastStrategy {
-    fn build_request(&self, address: &str, date: &str) -> Result<String, &'static str>;
+    fn provider(&self) -> WeatherService;
+
+    fn build_request(&self, target: &ForecastTarget) -> Result<ForecastQuery, &'static str>;
+
+    fn fetch(
+        &self,
+        query: &ForecastQuery,
+    ) -> impl Future<Output = Result<String, HttpError>> + Send {
+        let (url, provider) = (query.url.clone(), self.provider());
+        async move { http::get_text(&url, || quota::take(provider.into())).await }
+    }
+
+    /// Error from the provider's payload of a failed response.
+    fn parse_error(&self, status: u16, _body: &str) -> &'static str {
+        HttpError::status_error(status)
+    }
+
+    /// Time zone of the location from the response, for providers asked by address.
+    fn zone(&self, _body: &str) -> Option<Zone> {
+        None
+    }
 
     fn build_response(
         &self,
-        response: reqwest::blocking::Response,
+        query: &ForecastQuery,
+        body: &str,
     ) -> Result<ForecastResponseData, &'static str>;
+
+    /// Days from today to the query's date out of one response, for charts.
+    /// Days the provider doesn't have are left out.
+    fn build_days(
+        &self,
+        query: &ForecastQuery,
+        body: &str,
+    ) -> Result<Vec<ForecastResponseData>, &'static str> {
+        let last = &query.date_option;
+        let mut days = Vec::new();
+        let mut error = None;
+        for before in (0..=last.days_from_now.max(0)).rev() {
+            let day = ForecastQuery {
+                url: query.url.clone(),
+                date_option: DateOption {
+                    date: last.date - chrono::Duration::days(before),
+                    days_from_now: last.days_from_now - before,
+                },
+                place: query.place.clone(),
+            };
+            match self.build_response(&day, body) {
+                Ok(forecast) => days.push(forecast),
+                Err(day_error) => error = Some(day_error),
+            }
+        }
+        match (days.is_empty(), error) {
+            (true, Some(error)) => Err(error),
+            _ => Ok(days),
+        }
+    }
 }
 
 struct WeatherForecastData<T: ForecastStrategy> {
@@ -66,55 +383,246 @@ impl<T: ForecastStrategy> WeatherForecastData<T> {
         Self { forecast_strategy }
     }
 
-    fn get_forecast(
+    async fn request(
         &self,
-        address: &str,
-        date: &str,
-    ) -> Result<ForecastResponseData, &'static str> {
-        let results = match self.forecast_strategy.build_request(address, date) {
-            Ok(query) => match reqwest::blocking::get(query) {
-                Ok(response) => self.forecast_strategy.build_response(response),
-                Err(_) => Err("Can't retrieve weather data"),
-            },
-            Err(_) => Err("Can't create request"),
-        };
-        results
+        target: &ForecastTarget,
+    ) -> Result<(ForecastQuery, String), ForecastError> {
+        // Geocoding of a target without a place is still blocking.
+        let mut query =
+            tokio::task::block_in_place(|| self.forecast_strategy.build_request(target))
+                .map_err(|error| ErrorKind::Request.error(error))?;
+        let body =
+            self.forecast_strategy
+                .fetch(&query)
+                .await
+                .map_err(|error| {
+                    ErrorKind::from(&error).error(error.into_error(|status, body| {
+                        self.forecast_strategy.parse_error(status, body)
+                    }))
+                })?;
+        // Coordinates come from geocoding, which has the zone already.
+        if target.timezone.is_none() && query.place.is_none() {
+            match self.forecast_strategy.zone(&body) {
+                Some(zone) => {
+                    query.date_option = target
+                        .date_in(zone)
+                        .map_err(|error| ErrorKind::Request.error(error))?
+                }
+                None => eprintln!(
+                    "Warning: time zone of {} is unknown, dates are resolved on this machine",
+                    target.address
+                ),
+            }
+        }
+        Ok((query, body))
+    }
+
+    async fn get_forecast(
+        &self,
+        target: &ForecastTarget,
+    ) -> Result<ForecastResponseData, ForecastError> {
+        let (query, body) = self.request(target).await?;
+        let mut response_data = self
+            .forecast_strategy
+            .build_response(&query, &body)
+            .map_err(|error| ErrorKind::Response.error(error))?;
+        if target.timezone.is_some() {
+            response_data.timezone = target.timezone;
+        }
+        Ok(response_data)
+    }
+
+    async fn get_days(
+        &self,
+        target: &ForecastTarget,
+    ) -> Result<Vec<ForecastResponseData>, ForecastError> {
+        let (query, body) = self.request(target).await?;
+        let mut days = self
+            .forecast_strategy
+            .build_days(&query, &body)
+            .map_err(|error| ErrorKind::Response.error(error))?;
+        if target.timezone.is_some() {
+            for day in &mut days {
+                day.timezone = target.timezone;
+            }
+        }
+        Ok(days)
     }
 }
 
+#[derive(Serialize)]
 pub struct ForecastResponseData {
     pub location: String,
     pub date: String,
     pub max_temp: f32,
     pub min_temp: f32,
-    pub avg_temp: f32,
-    pub maxwind_kph: f32,
-    pub avghumidity: f32,
-    pub condition: String,
+    pub avg_temp: Reading,
+    pub maxwind_kph: Reading,
+    pub avghumidity: Reading,
+    pub pressure_mb: Reading,
+    pub uv_index: Reading,
+    pub visibility_km: Reading,
+    pub dew_point_c: Reading,
+    /// Percent of the sky covered by clouds.
+    pub cloud_cover: Reading,
+    /// Direction the wind comes from, degrees clockwise from the north.
+    pub wind_degree: Reading,
+    pub gust_kph: Reading,
+    pub condition: Condition,
+    pub condition_text: Option<String>,
+    pub precip_mm: Reading,
+    /// Chance of any precipitation during the day, percent.
+    pub precip_chance: Reading,
+    pub snow_cm: Reading,
+    pub timezone: Option<Zone>,
+    pub normal: Option<Anomaly>,
 }
 
 impl ForecastResponseData {
-    fn render(&self) {
+    /// Day with only the temperatures, tests set the other fields by struct update.
+    #[cfg(test)]
+    pub fn sample(location: &str, date: &str, min_temp: f32, max_temp: f32) -> Self {
+        ForecastResponseData {
+            location: location.to_string(),
+            date: date.to_string(),
+            max_temp,
+            min_temp,
+            avg_temp: Reading::UNAVAILABLE,
+            maxwind_kph: Reading::UNAVAILABLE,
+            avghumidity: Reading::UNAVAILABLE,
+            pressure_mb: Reading::UNAVAILABLE,
+            uv_index: Reading::UNAVAILABLE,
+            visibility_km: Reading::UNAVAILABLE,
+            dew_point_c: Reading::UNAVAILABLE,
+            cloud_cover: Reading::UNAVAILABLE,
+            wind_degree: Reading::UNAVAILABLE,
+            gust_kph: Reading::UNAVAILABLE,
+            condition: Condition::Unknown,
+            condition_text: None,
+            precip_mm: Reading::UNAVAILABLE,
+            precip_chance: Reading::UNAVAILABLE,
+            snow_cm: Reading::UNAVAILABLE,
+            timezone: None,
+            normal: None,
+        }
+    }
+
+    fn render(&self, options: &ForecastOptions) {
         println!(
             "== Weather forecast for {} on date {} ==",
             self.location, self.date
         );
-        if !self.condition.is_empty() {
-            let cond_emoji = match self.condition.to_lowercase().as_str() {
-                "sunny" => "☀️",
-                "overcast" => "🌥️",
-                "blizzard" | "snow" => "🌨️",
-                "patchy rain possible" => "🌦️",
-                "clouds" => "☁️",
-                _ => "",
-            };
-            println!("{} {}", self.condition, cond_emoji);
+        match (&self.condition_text, self.condition) {
+            (None, Condition::Unknown) => {}
+            (None, condition) => println!("{} {}", condition, condition.icon()),
+            (Some(text), condition) => println!("{} {}", text, condition.icon()),
+        }
+        if let Some(timezone) = self.timezone {
+            println!(
+                "{}Local time {} ({timezone})",
+                output::icon("🕒"),
+                timezone.now().format("%Y-%m-%d %H:%M")
+            );
+        }
+        match self.avg_temp.value {
+            Some(_) => println!(
+                "{}Average temperature {}°C (Min {}°C and Max {}°C)",
+                output::icon("🌡️"),
+                self.avg_temp,
+                self.min_temp,
+                self.max_temp
+            ),
+            None => println!(
+                "{}Temperature from {}°C to {}°C",
+                output::icon("🌡️"),
+                self.min_temp,
+                self.max_temp
+            ),
+        }
+        if self.avghumidity.value.is_some() {
+            println!(
+                "{}Average humidity {}",
+                output::icon("💧"),
+                self.avghumidity
+            );
+        }
+        match (self.maxwind_kph.value, self.wind_degree.value) {
+            (Some(_), Some(degree)) => println!(
+                "{}Wind max speed {} Km/h from {}{}",
+                output::icon("💨"),
+                self.maxwind_kph,
+                meteo::compass(degree),
+                match output::unicode() {
+                    true => format!(" {}", meteo::wind_arrow(degree)),
+                    false => String::new(),
+                }
+            ),
+            (Some(_), None) => println!(
+                "{}Wind max speed {} Km/h",
+                output::icon("💨"),
+                self.maxwind_kph
+            ),
+            (None, _) => {}
+        }
+        match (self.precip_mm.value, self.precip_chance.value) {
+            (Some(_), Some(_)) => println!(
+                "{}Precipitation {} mm, chance {}%",
+                output::icon("☔"),
+                self.precip_mm,
+                self.precip_chance
+            ),
+            (Some(_), None) => {
+                println!("{}Precipitation {} mm", output::icon("☔"), self.precip_mm)
+            }
+            (None, Some(_)) => println!(
+                "{}Chance of precipitation {}%",
+                output::icon("☔"),
+                self.precip_chance
+            ),
+            (None, None) => {}
+        }
+        if self.snow_cm.value.is_some() {
+            println!("{}Snowfall {} cm", output::icon("❄️"), self.snow_cm);
+        }
+        if options.detail == Detail::Full {
+            self.render_details();
+        }
+        if options.comfort {
+            self.render_comfort();
+        }
+        if let Some(normal) = &self.normal {
+            println!("{}{}", output::icon("📊"), normal.describe());
+        }
+    }
+
+    fn render_comfort(&self) {
+        let comfort = Comfort::new(self);
+        let lines = [
+            ("🤗", "Feels like", comfort.feels_like),
+            ("🥵", "Heat index up to", comfort.heat_index),
+            ("🥶", "Wind chill down to", comfort.wind_chill),
+            ("💦", "Humidex up to", comfort.humidex),
+        ];
+        for (emoji, label, reading) in lines {
+            if reading.value.is_some() {
+                println!("{}{label} {reading}°C", output::icon(emoji));
+            }
+        }
+    }
+
+    fn render_details(&self) {
+        let lines = [
+            ("🌬️", "Wind gusts up to", self.gust_kph, " Km/h"),
+            ("🧭", "Pressure", self.pressure_mb, " hPa"),
+            ("☁️", "Cloud cover", self.cloud_cover, "%"),
+            ("🌫️", "Visibility", self.visibility_km, " km"),
+            ("☀️", "UV index", self.uv_index, ""),
+            ("💦", "Dew point", self.dew_point_c, "°C"),
+        ];
+        for (emoji, label, reading, unit) in lines {
+            if reading.value.is_some() {
+                println!("{}{label} {reading}{unit}", output::icon(emoji));
+            }
         }
-        println!(
-            "🌡️  Average temperature {}°C (Min {}°C and Max {}°C)",
-            self.avg_temp, self.min_temp, self.max_temp
-        );
-        println!("💧  Average humidity {}", self.avghumidity);
-        println!("💨  Wind max speed {} Km/h", self.maxwind_kph);
     }
 }
@@ -1,5 +1,15 @@
-use crate::services::weather_service::{ForecastResponseData, ForecastStrategy};
-use crate::utils::date::DateOption;
+use crate::services::condition::Condition;
+use crate::services::geocoding::Place;
+use crate::services::history::HistoryStrategy;
+use crate::services::reading::{Provenance, Reading};
+use crate::services::weather_service::{
+    ForecastQuery, ForecastResponseData, ForecastStrategy, ForecastTarget, Source, WeatherService,
+};
+use crate::utils::date::{DateOption, Zone};
+use crate::utils::http::HttpError;
+use crate::utils::meteo;
+use chrono::NaiveDate;
+use chrono_tz::Tz;
 
 use serde::{Deserialize, Serialize};
 
@@ -18,38 +28,91 @@ https://api.weatherapi.com/v1/future.json?key=<api_key>&q=Vyshgorod, Ukraine&dt=
  */
 pub struct WeatherApiStrategy;
 
-const API_KEY: &str = env!("WEATHERAPI_API_KEY");
-const URL: &str = "https://api.weatherapi.com/v1/";
+pub const API_KEY: &str = env!("WEATHERAPI_API_KEY");
+pub const URL: &str = "https://api.weatherapi.com/v1/";
 
 impl ForecastStrategy for WeatherApiStrategy {
-    fn build_request(&self, address: &str, date: &str) -> Result<String, &'static str> {
-        let date = DateOption::new(date);
-        match WeatherRequest::new(address, date).query(API_KEY) {
-            Ok(query) => Ok(query),
+    fn provider(&self) -> WeatherService {
+        WeatherService::WeatherApi
+    }
+
+    fn parse_error(&self, status: u16, body: &str) -> &'static str {
+        parse_error(status, body)
+    }
+
+    fn build_request(&self, target: &ForecastTarget) -> Result<ForecastQuery, &'static str> {
+        let request = WeatherRequest::new(&target.address, target.date_option.clone());
+        match request.query(API_KEY) {
+            Ok(url) => Ok(ForecastQuery {
+                url,
+                date_option: request.date_option,
+                place: None,
+            }),
             Err(_) => Err("Can't build query"),
         }
     }
 
+    fn zone(&self, body: &str) -> Option<Zone> {
+        let data = serde_json::from_str::<WeatherApiResponse>(body).ok()?;
+        data.location.tz_id.parse::<Tz>().ok().map(Zone::from)
+    }
+
     fn build_response(
         &self,
-        request_result: reqwest::blocking::Response,
+        query: &ForecastQuery,
+        body: &str,
     ) -> Result<ForecastResponseData, &'static str> {
-        match request_result.json::<WeatherApiResponse>() {
-            Ok(data) => build_forecast(data),
+        match serde_json::from_str::<WeatherApiResponse>(body) {
+            Ok(data) => build_forecast(data, &query.date_option),
             Err(_) => Err("Incorrect data from weather service: can't parse"),
         }
     }
 }
 
-fn build_forecast(data: WeatherApiResponse) -> Result<ForecastResponseData, &'static str> {
-    let location = data.location;
-    let forecastday = match data.forecast.forecastday.last() {
-        Some(forecastday) => forecastday,
-        None => return Err("Error: No forecast for this day"),
+// Dates of `forecastday` are already local for the location.
+fn build_forecast(
+    data: WeatherApiResponse,
+    date_option: &DateOption,
+) -> Result<ForecastResponseData, &'static str> {
+    let date = date_option.to_string();
+    let provenance = if date_option.days_from_now < 0 {
+        Provenance::Measured
+    } else {
+        Provenance::Forecast
     };
+    match data
+        .forecast
+        .forecastday
+        .iter()
+        .find(|forecastday| forecastday.date == date)
+    {
+        Some(forecastday) => Ok(forecast_day(&data.location, forecastday, provenance)),
+        None => Err("Error: No forecast for this day"),
+    }
+}
+
+fn forecast_day(
+    location: &WeatherLocation,
+    forecastday: &WeatherForecastDay,
+    provenance: Provenance,
+) -> ForecastResponseData {
     let day = &forecastday.day;
+    let reading = |value: Option<f32>| Reading::new(value, provenance);
+    let hours = &forecastday.hour;
+    let hourly =
+        |field: fn(&Hour) -> Option<f32>| -> Vec<f32> { hours.iter().filter_map(field).collect() };
+    let hourly_average = |field: fn(&Hour) -> Option<f32>| {
+        Reading::new(meteo::average(&hourly(field)), Provenance::Derived)
+    };
+    let dew_point_c = match meteo::average(&hourly(|hour| hour.dewpoint_c)) {
+        Some(dew_point) => Reading::new(Some(dew_point), Provenance::Derived),
+        None => Reading::new(
+            meteo::dew_point(day.avgtemp_c, day.avghumidity),
+            Provenance::Derived,
+        ),
+    };
 
-    Ok(ForecastResponseData {
+    ForecastResponseData {
         location: format!(
             "{}, {}, {}",
             location.name, location.region, location.country
@@ -57,11 +120,103 @@ fn build_forecast(data: WeatherApiResponse) -> Result<ForecastResponseData, &'st
         date: forecastday.date.clone(),
         max_temp: day.maxtemp_c,
         min_temp: day.mintemp_c,
-        avg_temp: day.avgtemp_c,
-        maxwind_kph: day.maxwind_kph,
-        avghumidity: day.avghumidity,
-        condition: day.condition.text.clone(),
-    })
+        avg_temp: reading(Some(day.avgtemp_c)),
+        maxwind_kph: reading(Some(day.maxwind_kph)),
+        avghumidity: reading(Some(day.avghumidity)),
+        pressure_mb: hourly_average(|hour| hour.pressure_mb),
+        uv_index: reading(day.uv),
+        visibility_km: reading(day.avgvis_km),
+        dew_point_c,
+        cloud_cover: hourly_average(|hour| hour.cloud),
+        wind_degree: Reading::new(
+            meteo::mean_direction(&hourly(|hour| hour.wind_degree)),
+            Provenance::Derived,
+        ),
+        gust_kph: reading(hourly(|hour| hour.gust_kph).into_iter().reduce(f32::max)),
+        condition: Condition::from_weatherapi_code(day.condition.code),
+        condition_text: Some(day.condition.text.clone()).filter(|text| !text.is_empty()),
+        precip_mm: reading(Some(day.totalprecip_mm)),
+        precip_chance: reading(match (day.daily_chance_of_rain, day.daily_chance_of_snow) {
+            (Some(rain), Some(snow)) => Some(rain.max(snow)),
+            (rain, snow) => rain.or(snow),
+        }),
+        snow_cm: reading(day.totalsnow_cm),
+        timezone: location.tz_id.parse::<Tz>().ok().map(Zone::from),
+        normal: None,
+    }
+}
+
+/*
+History is requested day by day: `end_dt` for ranges is available only on paid plans.
+*/
+impl HistoryStrategy for WeatherApiStrategy {
+    fn provider(&self) -> Source {
+        Source::Forecast(WeatherService::WeatherApi)
+    }
+
+    fn parse_error(&self, status: u16, body: &str) -> &'static str {
+        parse_error(status, body)
+    }
+
+    fn earliest_date(&self) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2010, 1, 1).expect("valid date")
+    }
+
+    fn max_days_per_request(&self) -> i64 {
+        1
+    }
+
+    fn build_history_request(
+        &self,
+        address: &str,
+        _place: Option<&Place>,
+        from: NaiveDate,
+        _to: NaiveDate,
+    ) -> Result<String, &'static str> {
+        match history_query(address, from, API_KEY) {
+            Ok(query) => Ok(query),
+            Err(_) => Err("Can't build query"),
+        }
+    }
+
+    fn build_history_response(
+        &self,
+        _place: Option<&Place>,
+        body: &str,
+    ) -> Result<Vec<ForecastResponseData>, &'static str> {
+        match serde_json::from_str::<WeatherApiResponse>(body) {
+            Ok(data) => Ok(data
+                .forecast
+                .forecastday
+                .iter()
+                .map(|forecastday| forecast_day(&data.location, forecastday, Provenance::Measured))
+                .collect()),
+            Err(_) => Err("Incorrect data from weather service: can't parse"),
+        }
+    }
+}
+
+// Errors come as `{"error": {"code": 1006, "message": "No matching location found."}}`.
+fn parse_error(status: u16, body: &str) -> &'static str {
+    match serde_json::from_str::<ErrorResponse>(body).map(|response| response.error.code) {
+        Ok(1002 | 2006) => "Weather service refused the API key",
+        Ok(1003) => "No location given",
+        Ok(1006) => "No location found",
+        Ok(2007) => "Monthly request limit of the provider is reached",
+        Ok(2008) => "Weather service disabled the API key",
+        Ok(2009) => "Plan of the weather service doesn't include this data",
+        _ => HttpError::status_error(status),
+    }
+}
+
+fn history_query(address: &str, date: NaiveDate, api_key: &str) -> Result<String, url::ParseError> {
+    let mut url = url::Url::parse(URL)?.join("history.json")?;
+    url.query_pairs_mut()
+        .append_pair("dt", &date.format("%Y-%m-%d").to_string())
+        .append_pair("key", api_key)
+        .append_pair("q", address);
+
+    Ok(url.to_string())
 }
 
 struct WeatherRequest {
@@ -100,14 +255,25 @@ impl WeatherRequest {
 
     fn set_date_option(&self) -> String {
         match self.forecast_method().as_str() {
+            // One day more, until the response has the zone the location may be a day behind.
             "forecast.json" => {
-                "days=".to_string() + &(self.date_option.days_from_now + 1).to_string()
+                "days=".to_string() + &(self.date_option.days_from_now + 2).min(14).to_string()
             }
             _ => "dt=".to_string() + &self.date_option.to_string(),
         }
     }
 }
 
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: ErrorDetails,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorDetails {
+    code: u32,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct WeatherApiResponse {
     location: WeatherLocation,
@@ -119,6 +285,8 @@ struct WeatherLocation {
     name: String,
     region: String,
     country: String,
+    #[serde(default)]
+    tz_id: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -130,6 +298,9 @@ struct WeatherForecastDays {
 struct WeatherForecastDay {
     date: String,
     day: Day,
+    // Pressure, clouds, wind direction, gusts and dew point are only in the hourly forecast.
+    #[serde(default)]
+    hour: Vec<Hour>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -139,18 +310,46 @@ struct Day {
     avgtemp_c: f32,
     maxwind_kph: f32,
     avghumidity: f32,
-    condition: Condition,
+    totalprecip_mm: f32,
+    #[serde(default)]
+    totalsnow_cm: Option<f32>,
+    #[serde(default)]
+    daily_chance_of_rain: Option<f32>,
+    #[serde(default)]
+    daily_chance_of_snow: Option<f32>,
+    #[serde(default)]
+    avgvis_km: Option<f32>,
+    #[serde(default)]
+    uv: Option<f32>,
+    condition: DayCondition,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Hour {
+    #[serde(default)]
+    pressure_mb: Option<f32>,
+    #[serde(default)]
+    cloud: Option<f32>,
+    #[serde(default)]
+    wind_degree: Option<f32>,
+    #[serde(default)]
+    gust_kph: Option<f32>,
+    #[serde(default)]
+    dewpoint_c: Option<f32>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct Condition {
+struct DayCondition {
     text: String,
+    code: u32,
 }
 
 #[cfg(test)]
 mod tests {
-    use super::WeatherRequest;
-    use crate::utils::date::DateOption;
+    use super::{parse_error, WeatherApiStrategy, WeatherRequest};
+    use crate::services::reading::{Provenance, Reading};
+    use crate::services::weather_service::ForecastStrategy;
+    use crate::utils::date::{DateOption, Zone};
     use chrono::{Duration, Local, NaiveDate};
 
     #[test]
@@ -169,7 +368,7 @@ mod tests {
         let date = date_from_now(0);
         let query = query_for_date(date);
         let sample = "https://api.weatherapi.com/v1/forecast.json?".to_string()
-            + "days=1"
+            + "days=2"
             + "&key=some_api_key&q=test_city%2C+UA";
         assert_eq!(query, sample);
     }
@@ -195,6 +394,63 @@ mod tests {
         assert_eq!(query, sample);
     }
 
+    #[test]
+    fn history_request_for_date() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 5).unwrap();
+        let query = super::history_query("test_city, UA", date, "some_api_key").unwrap();
+        let sample = "https://api.weatherapi.com/v1/history.json?".to_string()
+            + "dt=2025-01-05"
+            + "&key=some_api_key&q=test_city%2C+UA";
+        assert_eq!(query, sample);
+    }
+
+    #[test]
+    fn zone_comes_from_the_response() {
+        let body = r#"{"location": {"name": "Los Angeles", "region": "California",
+            "country": "USA", "tz_id": "America/Los_Angeles"}, "forecast": {"forecastday": []}}"#;
+        assert_eq!(
+            WeatherApiStrategy.zone(body),
+            Some(Zone::Named(chrono_tz::America::Los_Angeles))
+        );
+        assert_eq!(WeatherApiStrategy.zone("{}"), None);
+    }
+
+    #[test]
+    fn forecast_day_with_precipitation() {
+        let location: super::WeatherLocation = serde_json::from_str(
+            r#"{"name": "Kyiv", "region": "Kyiv", "country": "Ukraine", "tz_id": "Europe/Kyiv"}"#,
+        )
+        .unwrap();
+        let forecastday: super::WeatherForecastDay = serde_json::from_str(
+            r#"{"date": "2025-01-05", "day": {
+                "maxtemp_c": 1.2, "mintemp_c": -3.4, "avgtemp_c": -1.0,
+                "maxwind_kph": 14.0, "avghumidity": 88.0,
+                "totalprecip_mm": 2.5, "totalsnow_cm": 1.8,
+                "daily_chance_of_rain": 20, "daily_chance_of_snow": 75,
+                "avgvis_km": 6.5, "uv": 1.0,
+                "condition": {"text": "Light snow", "code": 1213}
+            }, "hour": [
+                {"pressure_mb": 1010, "cloud": 100, "wind_degree": 350, "gust_kph": 20.2, "dewpoint_c": -2.0},
+                {"pressure_mb": 1014, "cloud": 80, "wind_degree": 10, "gust_kph": 25.6, "dewpoint_c": -3.0}
+            ]}"#,
+        )
+        .unwrap();
+        let forecast = super::forecast_day(&location, &forecastday, Provenance::Forecast);
+
+        assert_eq!(
+            forecast.precip_mm,
+            Reading::new(Some(2.5), Provenance::Forecast)
+        );
+        assert_eq!(forecast.precip_chance.value, Some(75.0));
+        assert_eq!(forecast.snow_cm.value, Some(1.8));
+        assert_eq!(forecast.pressure_mb, Reading::derived(1012.0));
+        assert_eq!(forecast.cloud_cover.value, Some(90.0));
+        assert_eq!(forecast.wind_degree.value, Some(0.0));
+        assert_eq!(forecast.gust_kph.value, Some(25.6));
+        assert_eq!(forecast.dew_point_c.value, Some(-2.5));
+        assert_eq!(forecast.visibility_km.value, Some(6.5));
+    }
+
     fn date_option_string(date: NaiveDate) -> String {
         let date_string = date.to_string();
         format!("date={date_string}")
@@ -206,9 +462,24 @@ mod tests {
 
     fn query_for_date(date: NaiveDate) -> String {
         let date_param = date_option_string(date);
-        let date_option = DateOption::new(&date_param);
+        let date_option = DateOption::new(&date_param, None).unwrap();
         WeatherRequest::new("test_city, UA", date_option)
             .query("some_api_key")
             .unwrap()
     }
+
+    #[test]
+    fn error_payloads() {
+        let body = r#"{"error":{"code":1006,"message":"No matching location found."}}"#;
+        assert_eq!(parse_error(400, body), "No location found");
+        let body = r#"{"error":{"code":2006,"message":"API key is invalid."}}"#;
+        assert_eq!(
+            parse_error(401, body),
+            "Weather service refused the API key"
+        );
+        assert_eq!(
+            parse_error(403, "{}"),
+            "Weather service refused the request"
+        );
+    }
 }
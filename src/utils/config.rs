@@ -1,64 +1,995 @@
+use crate::services::daemon::DaemonConfig;
+use crate::services::notify::SinkConfig;
+use crate::services::quota::QuotaLimit;
+use crate::services::watch::Rule;
 use crate::services::weather_service::WeatherService;
+use crate::utils::http::HttpConfig;
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use toml::value::Table;
+use toml::Value;
+
+/*
+Config is merged from layers, every next one wins over the previous ones:
+
+1. defaults
+2. system file /etc/weather-cli/config.toml
+3. user file, `weather config path` shows where it is
+4. project file .weathercli.toml in the current directory or the nearest parent
+5. profile chosen with --profile or WEATHER_CLI_PROFILE, `[profiles.<name>]` of any file
+6. environment variables WEATHER_CLI__<KEY>, parts of the key joined with `__`:
+   WEATHER_CLI__HTTP__TIMEOUT=30
+7. command line, --set key=value
+
+Tables are merged key by key, other values including arrays replace the previous ones:
+
+provider = "metno"
+addresses = ["Kyiv, UA"]
+
+[profiles.work]
+provider = "openweather"
+addresses = ["Berlin, DE"]
+http.proxy = "http://proxy.example.com:3128"
+
+Keys are dotted paths: `provider`, `http.timeout`, `sinks.team.url`.
+Values are TOML (`30`, `true`, `["Kyiv, UA"]`), anything else is a string.
+
+Every file has the `version` of its shape. Older files are upgraded by `MIGRATIONS` when they
+are read and saved upgraded the next time they are changed. A file which can't be read is skipped
+and invalid values are dropped with a warning, so the rest of the settings still apply;
+`weather config doctor --fix` repairs the files.
+*/
+
+const APP: &str = "weather-cli";
+const PROJECT_FILE: &str = ".weathercli.toml";
+const ENV_PREFIX: &str = "WEATHER_CLI__";
+// Steps upgrading a file by one version, the first one is from version 1 (no `version` key).
+const MIGRATIONS: [fn(&mut Table); 1] = [lowercase_providers];
+pub const CONFIG_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Scope {
+    System,
+    User,
+    Project,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 3] = [Scope::System, Scope::User, Scope::Project];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scope::System => "system",
+            Scope::User => "user",
+            Scope::Project => "project",
+        }
+    }
+}
+
+/// Profile and values from the command line.
+#[derive(Default, Debug)]
+pub struct ConfigOverrides {
+    pub profile: Option<String>,
+    /// `key=value` pairs.
+    pub values: Vec<String>,
+}
+
+struct Layer {
+    origin: String,
+    scope: Option<Scope>,
+    table: Table,
+    /// Why the layer can't be used, its table is empty then.
+    error: Option<String>,
+}
+
+/// Merged layers and the layer every value came from.
+struct Merged {
+    table: Table,
+    origins: BTreeMap<String, String>,
+}
+
+// Overrides are set once at start, before the config is read.
+static OVERRIDES: OnceLock<ConfigOverrides> = OnceLock::new();
+
+pub fn set_overrides(overrides: ConfigOverrides) {
+    let _ = OVERRIDES.set(overrides);
+}
+
+fn overrides() -> &'static ConfigOverrides {
+    OVERRIDES.get_or_init(ConfigOverrides::default)
+}
 
 pub fn set_provider(provider: Option<&str>) {
-    let hint = "Please use: openweather, weatherapi.";
+    let hint = "Please use: openweather, weatherapi, metno.";
 
     match WeatherService::from(provider) {
         WeatherService::MissingService => println!("No service provided. {hint}"),
         WeatherService::UnknownService => println!("Service unknown. {hint}"),
-        permitted_provider => {
-            write_config(permitted_provider);
-        }
+        permitted_provider => set("provider", permitted_provider.name(), Scope::User),
     }
 }
 
 pub fn get_provider() -> WeatherService {
-    let config: Result<WeatherCliConfig, confy::ConfyError> = confy::load("weather-cli", None);
-    match config {
-        Ok(config) => WeatherService::from(Some(config.provider.as_str())),
-        Err(_) => WeatherService::OpenWeather,
+    WeatherService::from(Some(load().provider.as_str()))
+}
+
+/// Whole config without the layers and values which are broken, see `try_load` for the errors.
+pub fn load() -> WeatherCliConfig {
+    match merge_layers(layers(), overrides().profile.as_deref()) {
+        Ok(mut merged) => {
+            salvage(&mut merged.table, "", |table| {
+                deserialize(&Merged {
+                    table: table.clone(),
+                    origins: BTreeMap::new(),
+                })
+                .map(|_| ())
+            });
+            deserialize(&merged).unwrap_or_default()
+        }
+        Err(_) => WeatherCliConfig::default(),
+    }
+}
+
+pub fn try_load() -> Result<WeatherCliConfig, String> {
+    let layers = layers();
+    if let Some(error) = layers.iter().find_map(|layer| layer.error.clone()) {
+        return Err(error);
     }
+    deserialize(&merge_layers(layers, overrides().profile.as_deref())?)
 }
 
 #[derive(Serialize, Deserialize)]
-struct WeatherCliConfig {
-    provider: String,
+#[serde(deny_unknown_fields)]
+pub struct WeatherCliConfig {
+    #[serde(default = "current_version")]
+    pub version: u32,
+    pub provider: String,
+    /// Addresses checked by `watch` and `daemon`.
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    /// Notification sinks by name, used by `get --notify`.
+    #[serde(default)]
+    pub sinks: BTreeMap<String, SinkConfig>,
+    /// Request limits by provider instead of the free plan ones.
+    #[serde(default)]
+    pub quota: HashMap<String, QuotaLimit>,
+    #[serde(default)]
+    pub http: HttpConfig,
 }
 
 impl Default for WeatherCliConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             provider: "openweather".to_string(),
+            addresses: Vec::new(),
+            rules: Vec::new(),
+            daemon: DaemonConfig::default(),
+            sinks: BTreeMap::new(),
+            quota: HashMap::new(),
+            http: HttpConfig::default(),
         }
     }
 }
 
-fn maybe_provider_to_string(provider: &Option<WeatherService>) -> Option<String> {
-    match provider {
-        Some(WeatherService::OpenWeather) => Some("openweather".to_string()),
-        Some(WeatherService::WeatherApi) => Some("weatherapi".to_string()),
-        // Some(WeatherService::AccuWeather) => Some("accuweather".to_string()),
-        // Some(WeatherService::AerisWeather) => Some("aerisweather".to_string()),
-        Some(_) => None,
-        None => None,
+fn current_version() -> u32 {
+    CONFIG_VERSION
+}
+
+/// Upgrades the file to the current version, tells whether anything was changed.
+fn migrate(table: &mut Table) -> Result<bool, String> {
+    let version = match table.get("version") {
+        Some(Value::Integer(version)) if *version >= 1 => *version as u32,
+        Some(_) => return Err("Incorrect config version".to_string()),
+        None => 1,
+    };
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "Config version {version} is newer than this weather-cli supports ({CONFIG_VERSION})"
+        ));
+    }
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(table);
     }
+    let changed = !table.contains_key("version") || version < CONFIG_VERSION;
+    table.insert("version".to_string(), Value::Integer(CONFIG_VERSION.into()));
+    Ok(changed)
 }
 
-fn write_config(provider: WeatherService) {
-    match maybe_provider_to_string(&Some(provider)) {
-        Some(provider_str) => {
-            match confy::store(
-                "weather-cli",
-                None,
-                WeatherCliConfig {
-                    provider: provider_str,
-                },
-            ) {
-                Err(err) => print!("Can't save config: {err}"),
-                Ok(_) => println!("Config updated"),
+// Version 1 accepted providers in any case, like "OpenWeather".
+fn lowercase_providers(table: &mut Table) {
+    if let Some(Value::String(provider)) = table.get_mut("provider") {
+        *provider = provider.to_lowercase();
+    }
+    if let Some(Value::Table(profiles)) = table.get_mut("profiles") {
+        for (_, profile) in profiles.iter_mut() {
+            if let Value::Table(profile) = profile {
+                lowercase_providers(profile);
+            }
+        }
+    }
+}
+
+pub fn path(scope: Scope) -> Option<PathBuf> {
+    match scope {
+        Scope::System if cfg!(windows) => {
+            env::var_os("PROGRAMDATA").map(|dir| PathBuf::from(dir).join(APP).join("config.toml"))
+        }
+        Scope::System => Some(PathBuf::from("/etc").join(APP).join("config.toml")),
+        // The file written by earlier versions.
+        Scope::User => directories::ProjectDirs::from("rs", "", APP)
+            .map(|dirs| dirs.config_dir().join("default-config.toml")),
+        Scope::Project => {
+            let current = env::current_dir().ok()?;
+            let found = current
+                .ancestors()
+                .map(|dir| dir.join(PROJECT_FILE))
+                .find(|path| path.is_file());
+            Some(found.unwrap_or_else(|| current.join(PROJECT_FILE)))
+        }
+    }
+}
+
+/// File as it is, empty when there is no file.
+fn read_raw(path: &Path) -> Result<Table, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => toml::from_str(&content)
+            .map_err(|error| format!("Can't parse {}: {error}", path.display())),
+        Err(_) => Ok(Table::new()),
+    }
+}
+
+fn read_file(path: &Path) -> Result<Table, String> {
+    let mut table = read_raw(path)?;
+    if !table.is_empty() {
+        migrate(&mut table).map_err(|error| format!("{error} in {}", path.display()))?;
+    }
+    Ok(table)
+}
+
+impl Layer {
+    fn new(origin: String, scope: Option<Scope>, table: Result<Table, String>) -> Self {
+        match table {
+            Ok(table) => Layer {
+                origin,
+                scope,
+                table,
+                error: None,
+            },
+            Err(error) => Layer {
+                origin,
+                scope,
+                table: Table::new(),
+                error: Some(error),
+            },
+        }
+    }
+}
+
+fn layers() -> Vec<Layer> {
+    let mut layers = vec![Layer::new("default".to_string(), None, Ok(default_table()))];
+    for scope in Scope::ALL {
+        if let Some(path) = path(scope) {
+            layers.push(Layer::new(
+                path.display().to_string(),
+                Some(scope),
+                read_file(&path),
+            ));
+        }
+    }
+    let variables = env::vars().filter_map(|(name, value)| {
+        name.strip_prefix(ENV_PREFIX)
+            .map(|key| (key.to_lowercase().replace("__", "."), value))
+    });
+    layers.push(Layer::new(
+        "environment".to_string(),
+        None,
+        values_table(variables),
+    ));
+    let values = overrides()
+        .values
+        .iter()
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (key.trim().to_string(), value.trim().to_string()),
+            None => (pair.trim().to_string(), String::new()),
+        });
+    layers.push(Layer::new(
+        "command line".to_string(),
+        None,
+        values_table(values),
+    ));
+    layers
+}
+
+fn default_table() -> Table {
+    match Value::try_from(WeatherCliConfig::default()) {
+        Ok(Value::Table(table)) => table,
+        _ => Table::new(),
+    }
+}
+
+fn values_table(values: impl Iterator<Item = (String, String)>) -> Result<Table, String> {
+    let mut table = Table::new();
+    for (key, value) in values {
+        set_path(&mut table, &key, parse_value(&value))
+            .map_err(|error| format!("{error}: `{key}`"))?;
+    }
+    Ok(table)
+}
+
+/// TOML value, or the string itself.
+fn parse_value(raw: &str) -> Value {
+    match toml::from_str::<Table>(&format!("value = {raw}")) {
+        Ok(mut table) => table
+            .remove("value")
+            .unwrap_or_else(|| Value::String(raw.to_string())),
+        Err(_) => Value::String(raw.to_string()),
+    }
+}
+
+// Layers after the files are on top of the profile.
+fn merge_layers(layers: Vec<Layer>, profile: Option<&str>) -> Result<Merged, String> {
+    let mut merged = Merged {
+        table: Table::new(),
+        origins: BTreeMap::new(),
+    };
+    let mut profiles = Table::new();
+    let mut profile_merged = profile.is_none();
+    let mut after_files = false;
+    for mut layer in layers {
+        after_files |= layer.scope.is_some();
+        if after_files && layer.scope.is_none() && !profile_merged {
+            merge_profile(&mut merged, &mut profiles, profile)?;
+            profile_merged = true;
+        }
+        if let Some(Value::Table(table)) = layer.table.remove("profiles") {
+            merge(
+                &mut profiles,
+                table,
+                "",
+                &layer.origin,
+                &mut BTreeMap::new(),
+            );
+        }
+        merge(
+            &mut merged.table,
+            layer.table,
+            "",
+            &layer.origin,
+            &mut merged.origins,
+        );
+    }
+    if !profile_merged {
+        merge_profile(&mut merged, &mut profiles, profile)?;
+    }
+    Ok(merged)
+}
+
+fn merge_profile(
+    merged: &mut Merged,
+    profiles: &mut Table,
+    profile: Option<&str>,
+) -> Result<(), String> {
+    if let Some(name) = profile {
+        match profiles.remove(name) {
+            Some(Value::Table(table)) => merge(
+                &mut merged.table,
+                table,
+                "",
+                &format!("profile {name}"),
+                &mut merged.origins,
+            ),
+            Some(_) => return Err(format!("Profile `{name}` must be a table")),
+            None => return Err(format!("Unknown profile `{name}`")),
+        }
+    }
+    Ok(())
+}
+
+fn merge(
+    base: &mut Table,
+    layer: Table,
+    prefix: &str,
+    origin: &str,
+    origins: &mut BTreeMap<String, String>,
+) {
+    for (key, value) in layer {
+        let path = match prefix {
+            "" => key.clone(),
+            prefix => format!("{prefix}.{key}"),
+        };
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(table)) => {
+                merge(base, table, &path, origin, origins)
+            }
+            (_, value) => {
+                origins.retain(|key, _| key != &path && !key.starts_with(&format!("{path}.")));
+                record_origins(&value, &path, origin, origins);
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn record_origins(value: &Value, path: &str, origin: &str, origins: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Table(table) if !table.is_empty() => {
+            for (key, value) in table {
+                record_origins(value, &format!("{path}.{key}"), origin, origins);
+            }
+        }
+        _ => {
+            origins.insert(path.to_string(), origin.to_string());
+        }
+    }
+}
+
+fn deserialize(merged: &Merged) -> Result<WeatherCliConfig, String> {
+    let config: WeatherCliConfig = Value::Table(merged.table.clone())
+        .try_into()
+        .map_err(|error| with_origin(format!("Invalid config: {error}"), &merged.origins))?;
+    match WeatherService::from(Some(config.provider.as_str())) {
+        provider if !provider.has_forecast() => Err(with_origin(
+            format!(
+                "Invalid config: unknown provider `{}` for key `provider`, \
+                 please use: openweather, weatherapi, metno",
+                config.provider
+            ),
+            &merged.origins,
+        )),
+        _ => Ok(config),
+    }
+}
+
+/// Key named in the error of deserialization.
+fn error_key(error: &str) -> &str {
+    match error.find("for key `") {
+        Some(index) => error[index + 9..].split('`').next().unwrap_or_default(),
+        None => error.split('`').nth(1).unwrap_or_default(),
+    }
+}
+
+// Where the key named in the error was set.
+fn with_origin(error: String, origins: &BTreeMap<String, String>) -> String {
+    match origins.get(error_key(&error)) {
+        Some(origin) => format!("{error} (set in {origin})"),
+        None => error,
+    }
+}
+
+/// Removes values named in the errors of `check` until it passes, gives back the removed keys
+/// with their errors. `prefix` is where the checked values are in the table.
+fn salvage(
+    table: &mut Table,
+    prefix: &str,
+    check: impl Fn(&Table) -> Result<(), String>,
+) -> Vec<(String, String)> {
+    let mut removed = Vec::new();
+    while let Err(error) = check(table) {
+        let key = format!("{prefix}{}", error_key(&error));
+        if !remove_path(table, &key) {
+            break;
+        }
+        removed.push((key, error));
+    }
+    removed
+}
+
+fn get_path<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let mut parts = key.split('.');
+    let mut value = table.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_table()?.get(part)?;
+    }
+    Some(value)
+}
+
+fn set_path(table: &mut Table, key: &str, value: Value) -> Result<(), &'static str> {
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err("Incorrect config key");
+    }
+    let (last, parents) = parts.split_last().ok_or("Incorrect config key")?;
+    let mut table = table;
+    for part in parents {
+        let entry = table
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Table(Table::new()));
+        table = match entry {
+            Value::Table(table) => table,
+            _ => return Err("Config key is inside a value which isn't a table"),
+        };
+    }
+    table.insert(last.to_string(), value);
+    Ok(())
+}
+
+fn remove_path(table: &mut Table, key: &str) -> bool {
+    match key.split_once('.') {
+        Some((first, rest)) => match table.get_mut(first) {
+            Some(Value::Table(inner)) => {
+                let removed = remove_path(inner, rest);
+                if inner.is_empty() {
+                    table.remove(first);
+                }
+                removed
+            }
+            _ => false,
+        },
+        None => table.remove(key).is_some(),
+    }
+}
+
+// Key in the file, inside the profile when one is chosen.
+fn scoped_key(key: &str) -> String {
+    match &overrides().profile {
+        Some(profile) => format!("profiles.{profile}.{key}"),
+        None => key.to_string(),
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        Value::Table(_) => toml::to_string_pretty(value)
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+        value => value.to_string(),
+    }
+}
+
+// One line value for the list.
+fn inline(value: &Value) -> String {
+    match value {
+        Value::Table(table) => {
+            let values: Vec<String> = table
+                .iter()
+                .map(|(key, value)| format!("{key} = {}", inline(value)))
+                .collect();
+            match values.is_empty() {
+                true => "{}".to_string(),
+                false => format!("{{ {} }}", values.join(", ")),
             }
         }
-        None => println!("not write"),
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(inline).collect();
+            format!("[{}]", values.join(", "))
+        }
+        value => value.to_string(),
+    }
+}
+
+// Merged layers with warnings about the ones which can't be used.
+fn merged_with_warnings() -> Result<Merged, String> {
+    let layers = layers();
+    for error in layers.iter().filter_map(|layer| layer.error.as_ref()) {
+        eprintln!("Warning: {error}");
+    }
+    merge_layers(layers, overrides().profile.as_deref())
+}
+
+pub fn get(key: &str) {
+    let merged = match merged_with_warnings() {
+        Ok(merged) => merged,
+        Err(error) => return println!("Error: {error}"),
+    };
+    match get_path(&merged.table, key) {
+        Some(value) => println!("{}", display(value)),
+        None => println!("Error: `{key}` isn't set"),
+    }
+}
+
+pub fn list() {
+    let merged = match merged_with_warnings() {
+        Ok(merged) => merged,
+        Err(error) => return println!("Error: {error}"),
+    };
+    for (key, origin) in &merged.origins {
+        if let Some(value) = get_path(&merged.table, key) {
+            println!("{key} = {}  # {origin}", inline(value));
+        }
+    }
+}
+
+pub fn paths() {
+    for scope in Scope::ALL {
+        match path(scope) {
+            Some(path) => {
+                let state = match path.is_file() {
+                    true => "",
+                    false => " (missing)",
+                };
+                println!("{:<8} {}{state}", scope.name(), path.display());
+            }
+            None => println!("{:<8} -", scope.name()),
+        }
+    }
+}
+
+pub fn set(key: &str, value: &str, scope: Scope) {
+    update(scope, |table| {
+        set_path(table, &scoped_key(key), parse_value(value))
+    })
+}
+
+pub fn unset(key: &str, scope: Scope) {
+    update(scope, |table| match remove_path(table, &scoped_key(key)) {
+        true => Ok(()),
+        false => Err("Key isn't set in this file"),
+    })
+}
+
+// Changes the file of the scope when the whole config stays valid.
+fn update(scope: Scope, change: impl FnOnce(&mut Table) -> Result<(), &'static str>) {
+    let path = match path(scope) {
+        Some(path) => path,
+        None => return println!("Error: No config file for this scope"),
+    };
+    let mut layers = layers();
+    let layer = match layers.iter_mut().find(|layer| layer.scope == Some(scope)) {
+        Some(layer) => layer,
+        None => return println!("Error: No config file for this scope"),
+    };
+    if let Some(error) = &layer.error {
+        return println!("Error: {error}");
+    }
+    if let Err(error) = migrate(&mut layer.table) {
+        return println!("Error: {error}");
+    }
+    if let Err(error) = change(&mut layer.table) {
+        return println!("Error: {error}");
+    }
+    // The profile stays when its last key is removed.
+    if let Some(profile) = &overrides().profile {
+        let key = format!("profiles.{profile}");
+        if get_path(&layer.table, &key).is_none() {
+            let _ = set_path(&mut layer.table, &key, Value::Table(Table::new()));
+        }
+    }
+    let table = layer.table.clone();
+    if let Err(error) =
+        merge_layers(layers, overrides().profile.as_deref()).and_then(|merged| deserialize(&merged))
+    {
+        return println!("Error: Not saved. {error}");
+    }
+    match write_file(&path, &table) {
+        Ok(()) => println!("Config updated"),
+        Err(error) => println!("Error: {error}"),
+    }
+}
+
+fn write_file(path: &Path, table: &Table) -> Result<(), String> {
+    // Value puts plain values before tables, which TOML requires.
+    let content = toml::to_string_pretty(&Value::Table(table.clone()))
+        .map_err(|_| "Can't serialize config".to_string())?;
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    fs::write(path, content).map_err(|error| format!("Can't save config: {error}"))
+}
+
+/// Checks every config file alone and with its profiles, then all layers together.
+/// With `fix` upgrades the files and removes invalid values, the old file is kept as .bak.
+pub fn doctor(fix: bool) {
+    let mut failed = false;
+    for scope in Scope::ALL {
+        let path = match path(scope) {
+            Some(path) if path.is_file() => path,
+            _ => continue,
+        };
+        let label = path.display().to_string();
+        match diagnose(&path, scope) {
+            Err(error) => {
+                failed = true;
+                println!("error  {error}");
+                println!(
+                    "       edit it: weather config edit --scope {}",
+                    scope.name()
+                );
+            }
+            Ok((_, fixes, Some(error))) => {
+                failed = true;
+                for fix in fixes {
+                    println!("warn   {label}: {fix}");
+                }
+                println!("error  {error}");
+                println!(
+                    "       edit it: weather config edit --scope {}",
+                    scope.name()
+                );
+            }
+            Ok((_, fixes, None)) if fixes.is_empty() => {
+                println!("ok     {label} (version {CONFIG_VERSION})")
+            }
+            Ok((table, fixes, None)) if fix => {
+                let backup = path.with_extension("toml.bak");
+                let saved = fs::copy(&path, &backup)
+                    .map_err(|error| format!("Can't back up config: {error}"))
+                    .and_then(|_| write_file(&path, &table));
+                match saved {
+                    Ok(()) => {
+                        for fix in fixes {
+                            println!("fixed  {label}: {fix}");
+                        }
+                        println!("       old file is {}", backup.display());
+                    }
+                    Err(error) => {
+                        failed = true;
+                        println!("error  {label}: {error}");
+                    }
+                }
+            }
+            Ok((_, fixes, None)) => {
+                failed = true;
+                for fix in fixes {
+                    println!("warn   {label}: {fix}");
+                }
+            }
+        }
+    }
+    match try_load() {
+        Ok(_) => println!("ok     settings with all layers"),
+        Err(error) => {
+            failed = true;
+            println!("error  {error}");
+        }
+    }
+    if failed && !fix {
+        println!("Run `weather config doctor --fix` to repair what can be repaired");
+    }
+}
+
+/// Problems of the file of the scope which `config doctor --fix` repairs, or the error
+/// it can't repair. `None` when there is no file.
+pub fn file_status(scope: Scope) -> Option<(PathBuf, Result<Vec<String>, String>)> {
+    let path = path(scope).filter(|path| path.is_file())?;
+    let status = match diagnose(&path, scope) {
+        Ok((_, _, Some(error))) | Err(error) => Err(error),
+        Ok((_, fixes, None)) => Ok(fixes),
+    };
+    Some((path, status))
+}
+
+/// Repaired file, what was repaired and the error which can't be repaired.
+fn diagnose(path: &Path, scope: Scope) -> Result<(Table, Vec<String>, Option<String>), String> {
+    let label = path.display().to_string();
+    let mut table = read_raw(path)?;
+    let mut fixes = Vec::new();
+    if migrate(&mut table).map_err(|error| format!("{label}: {error}"))? {
+        fixes.push(format!("upgrade to version {CONFIG_VERSION}"));
+    }
+    let check = |table: &Table, profile: Option<&str>| {
+        let layers = vec![
+            Layer::new("default".to_string(), None, Ok(default_table())),
+            Layer::new(label.clone(), Some(scope), Ok(table.clone())),
+        ];
+        merge_layers(layers, profile)
+            .and_then(|merged| deserialize(&merged))
+            .map(|_| ())
+    };
+    let profiles: Vec<String> = match table.get("profiles") {
+        Some(Value::Table(profiles)) => profiles.keys().cloned().collect(),
+        _ => Vec::new(),
+    };
+    let mut removed = salvage(&mut table, "", |table| check(table, None));
+    for profile in &profiles {
+        let prefix = format!("profiles.{profile}.");
+        removed.extend(salvage(&mut table, &prefix, |table| {
+            check(table, Some(profile))
+        }));
+    }
+    fixes.extend(
+        removed
+            .into_iter()
+            .map(|(key, error)| format!("remove `{key}`, {error}")),
+    );
+    let error = std::iter::once(None)
+        .chain(profiles.iter().map(|profile| Some(profile.as_str())))
+        .find_map(|profile| check(&table, profile).err());
+    Ok((table, fixes, error))
+}
+
+/// Opens the file of the scope in $VISUAL or $EDITOR and checks it afterwards.
+pub fn edit(scope: Scope) {
+    let path = match path(scope) {
+        Some(path) => path,
+        None => return println!("Error: No config file for this scope"),
+    };
+    if !path.exists() {
+        let mut table = Table::new();
+        let _ = migrate(&mut table);
+        if let Err(error) = write_file(&path, &table) {
+            return println!("Error: {error}");
+        }
+    }
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| match cfg!(windows) {
+            true => "notepad".to_string(),
+            false => "vi".to_string(),
+        });
+    let mut words = editor.split_whitespace();
+    let program = match words.next() {
+        Some(program) => program,
+        None => return println!("Error: Editor is empty"),
+    };
+    match Command::new(program).args(words).arg(&path).status() {
+        Ok(status) if status.success() => match try_load() {
+            Ok(_) => println!("Config is valid"),
+            Err(error) => println!("Error: {error}"),
+        },
+        Ok(_) => println!("Error: Editor failed"),
+        Err(_) => println!("Error: Can't start editor `{program}`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        deserialize, diagnose, merge_layers, migrate, parse_value, remove_path, set_path, Layer,
+        Scope, CONFIG_VERSION,
+    };
+    use std::fs;
+    use toml::value::Table;
+    use toml::Value;
+
+    fn layer(origin: &str, scope: Option<Scope>, content: &str) -> Layer {
+        Layer::new(
+            origin.to_string(),
+            scope,
+            Ok(toml::from_str(content).unwrap()),
+        )
+    }
+
+    fn layers() -> Vec<Layer> {
+        vec![
+            layer(
+                "default",
+                None,
+                "provider = 'openweather'\n[http]\ntimeout = 20\nretries = 2",
+            ),
+            layer(
+                "user",
+                Some(Scope::User),
+                "provider = 'metno'\naddresses = ['Kyiv, UA']\n\
+                 [profiles.work]\nprovider = 'weatherapi'\naddresses = ['Berlin, DE']",
+            ),
+            layer("project", Some(Scope::Project), "[http]\ntimeout = 30"),
+            layer("environment", None, "[http]\nretries = 5"),
+        ]
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let merged = merge_layers(layers(), None).unwrap();
+        let config = deserialize(&merged).unwrap();
+        assert_eq!(config.provider, "metno");
+        assert_eq!(config.http.timeout, 30);
+        assert_eq!(config.http.retries, 5);
+        assert_eq!(merged.origins["http.timeout"], "project");
+        assert_eq!(merged.origins["http.retries"], "environment");
+        assert_eq!(merged.origins["provider"], "user");
+    }
+
+    #[test]
+    fn profile_is_over_files_and_under_environment() {
+        let mut layers = layers();
+        layers.push(layer("command line", None, "provider = 'weatherapi'"));
+        let merged = merge_layers(layers, Some("work")).unwrap();
+        let config = deserialize(&merged).unwrap();
+        assert_eq!(config.provider, "weatherapi");
+        assert_eq!(config.addresses, vec!["Berlin, DE".to_string()]);
+        assert_eq!(merged.origins["addresses"], "profile work");
+
+        assert_eq!(
+            merge_layers(self::layers(), Some("home")).err(),
+            Some("Unknown profile `home`".to_string())
+        );
+    }
+
+    #[test]
+    fn errors_name_the_key_and_file() {
+        let mut layers = layers();
+        layers.push(layer(
+            "project",
+            Some(Scope::Project),
+            "[http]\ntimeout = 'long'",
+        ));
+        let error = deserialize(&merge_layers(layers, None).unwrap())
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            "Invalid config: invalid type: string \"long\", expected u64 for key `http.timeout` (set in project)"
+        );
+
+        let mut layers = self::layers();
+        layers.push(layer("user", Some(Scope::User), "adresses = []"));
+        let error = deserialize(&merge_layers(layers, None).unwrap())
+            .err()
+            .unwrap();
+        assert!(error.starts_with("Invalid config: unknown field `adresses`"));
+        assert!(error.ends_with("(set in user)"));
+
+        let mut layers = self::layers();
+        layers.push(layer("environment", None, "provider = 'accuweather'"));
+        let error = deserialize(&merge_layers(layers, None).unwrap())
+            .err()
+            .unwrap();
+        assert!(error.contains("unknown provider `accuweather`"));
+    }
+
+    #[test]
+    fn keys_and_values() {
+        let mut table = Table::new();
+        set_path(&mut table, "http.timeout", parse_value("30")).unwrap();
+        set_path(
+            &mut table,
+            "sinks.team.url",
+            parse_value("https://example.com"),
+        )
+        .unwrap();
+        set_path(&mut table, "addresses", parse_value("['Kyiv, UA']")).unwrap();
+        assert_eq!(table["http"]["timeout"], Value::Integer(30));
+        assert_eq!(
+            table["sinks"]["team"]["url"],
+            Value::String("https://example.com".to_string())
+        );
+        assert_eq!(table["addresses"].as_array().unwrap().len(), 1);
+        assert!(set_path(&mut table, "http.timeout.value", parse_value("1")).is_err());
+        assert!(set_path(&mut table, "http..timeout", parse_value("1")).is_err());
+
+        assert!(remove_path(&mut table, "sinks.team.url"));
+        assert!(!table.contains_key("sinks"));
+        assert!(!remove_path(&mut table, "http.retries"));
+    }
+
+    #[test]
+    fn old_files_are_migrated() {
+        let mut table: Table =
+            toml::from_str("provider = 'OpenWeather'\n[profiles.work]\nprovider = 'MetNo'")
+                .unwrap();
+        assert_eq!(migrate(&mut table), Ok(true));
+        assert_eq!(table["version"], Value::Integer(CONFIG_VERSION.into()));
+        assert_eq!(table["provider"].as_str(), Some("openweather"));
+        assert_eq!(
+            table["profiles"]["work"]["provider"].as_str(),
+            Some("metno")
+        );
+        assert_eq!(migrate(&mut table), Ok(false));
+
+        let mut table: Table = toml::from_str("version = 99").unwrap();
+        assert!(migrate(&mut table).unwrap_err().contains("newer"));
+    }
+
+    #[test]
+    fn doctor_removes_invalid_values() {
+        let path = std::env::temp_dir().join(format!("weather-doctor-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "provider = 'metno'\naddresses = ['Kyiv, UA']\n[http]\ntimeout = 'long'\n\
+             [profiles.work]\nprovider = 'accuweather'\naddresses = ['Berlin, DE']",
+        )
+        .unwrap();
+        let (table, fixes, error) = diagnose(&path, Scope::User).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error, None);
+        assert_eq!(fixes.len(), 3);
+        assert!(fixes[0].starts_with("upgrade to version"));
+        assert!(fixes[1].starts_with("remove `http.timeout`"));
+        assert!(fixes[2].starts_with("remove `profiles.work.provider`"));
+        assert_eq!(table["provider"].as_str(), Some("metno"));
+        assert!(table["profiles"]["work"].get("addresses").is_some());
     }
 }
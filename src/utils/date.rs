@@ -1,62 +1,231 @@
-use chrono::{Local, NaiveDate};
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, Utc, Weekday,
+};
+use chrono_tz::Tz;
 use regex::Regex;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
 
+const DATE_HINT: &str = "Can't parse date, please use: today, tomorrow, friday, next monday, +3d, -2w, dd-mm, dd.mm.yyyy, yyyy-mm-dd or 2023-W10";
+
+/// Time zone of the forecast location: named one from geocoding or provider (`Europe/Kyiv`),
+/// or just UTC offset when provider gives nothing else.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zone {
+    Named(Tz),
+    Offset(FixedOffset),
+}
+
+impl Zone {
+    pub fn from_offset_seconds(seconds: i32) -> Option<Self> {
+        FixedOffset::east_opt(seconds).map(Zone::Offset)
+    }
+
+    pub fn local(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Named(tz) => time.with_timezone(tz).naive_local(),
+            Zone::Offset(offset) => time.with_timezone(offset).naive_local(),
+        }
+    }
+
+    pub fn now(&self) -> NaiveDateTime {
+        self.local(Utc::now())
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Zone::Named(tz) => write!(f, "{}", tz.name()),
+            Zone::Offset(offset) => write!(f, "UTC{offset}"),
+        }
+    }
+}
+
+/// IANA name (`America/Los_Angeles`) or UTC offset (`+02:00`, `UTC-5`).
+impl FromStr for Zone {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let hint = "Unknown time zone, please use IANA name like Europe/Kyiv or offset like UTC+2";
+        if let Ok(tz) = value.parse::<Tz>() {
+            return Ok(Zone::Named(tz));
+        }
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = PATTERN.get_or_init(|| {
+            Regex::new(r"^(?i:utc|gmt)?([+-])(\d{1,2})(?::?(\d{2}))?$").expect("valid regex")
+        });
+        let captures = pattern.captures(value.trim()).ok_or(hint)?;
+        let hours = captures[2].parse::<i32>().or(Err(hint))?;
+        let minutes = match captures.get(3) {
+            Some(minutes) => minutes.as_str().parse::<i32>().or(Err(hint))?,
+            None => 0,
+        };
+        let sign = if &captures[1] == "-" { -1 } else { 1 };
+        Zone::from_offset_seconds(sign * (hours * 3600 + minutes * 60)).ok_or(hint)
+    }
+}
+
+impl Serialize for Zone {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl From<Tz> for Zone {
+    fn from(tz: Tz) -> Self {
+        Zone::Named(tz)
+    }
+}
+
+#[derive(Clone)]
 pub struct DateOption {
     pub date: NaiveDate,
     pub days_from_now: i64,
 }
 
 impl DateOption {
-    pub fn new(date_str: &str) -> Self {
-        let parsed_date = match date_str {
-            "date=now" => Local::now().date_naive(),
-            date_str => match cut_date(date_str) {
-                Some(date_str) => match NaiveDate::parse_from_str(&date_str, "%Y-%m-%d") {
-                    Ok(date_time) => date_time,
-                    Err(_) => Local::now().date_naive(),
-                },
-                None => Local::now().date_naive(),
-            },
+    /// Dates are relative to today in the given zone, or on this machine when it's unknown.
+    pub fn new(date_str: &str, zone: Option<Zone>) -> Result<Self, &'static str> {
+        Self::parse(date_str, today_at(zone, Utc::now()))
+    }
+
+    /// Accepts the value with or without `date=` prefix, relative to `today`.
+    fn parse(date_str: &str, today: NaiveDate) -> Result<Self, &'static str> {
+        let value = date_str.trim();
+        let value = value.strip_prefix("date=").unwrap_or(value).to_lowercase();
+        let parsed_date = match parse_date(&value, today) {
+            Some(parsed_date) => parsed_date,
+            None => return Err(DATE_HINT),
         };
-        let diff = parsed_date.signed_duration_since(Local::now().date_naive());
-        let days_from_now = diff.num_days();
-        Self {
+        let days_from_now = parsed_date.signed_duration_since(today).num_days();
+        Ok(Self {
             date: parsed_date,
             days_from_now,
-        }
+        })
     }
 }
 
-fn cut_date(date_str: &str) -> Option<String> {
-    let pattern = match Regex::new(r"date=(\d{4}-\d{2}-\d{2})") {
-        Ok(pattern) => Some(pattern),
-        Err(_) => None,
+fn today_at(zone: Option<Zone>, now: DateTime<Utc>) -> NaiveDate {
+    match zone {
+        Some(zone) => zone.local(now).date(),
+        None => now.with_timezone(&Local).date_naive(),
+    }
+}
+
+fn parse_date(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match value {
+        "now" | "today" => Some(today),
+        "tomorrow" => today.succ_opt(),
+        "yesterday" => today.pred_opt(),
+        value => parse_weekday(value, today)
+            .or_else(|| parse_offset(value, today))
+            .or_else(|| parse_iso_week(value))
+            .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok())
+            .or_else(|| NaiveDate::parse_from_str(value, "%d.%m.%Y").ok())
+            .or_else(|| parse_day_month(value, today)),
+    }
+}
+
+// `friday` is the nearest one starting from today, `next friday` is the one in the next week
+// and `last friday` is the one in the previous week.
+fn parse_weekday(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let (week_shift, weekday) = match value.split_once(' ') {
+        Some(("next", weekday)) => (Some(1), weekday),
+        Some(("last", weekday)) => (Some(-1), weekday),
+        Some(_) => return None,
+        None => (None, value),
     };
+    let weekday = weekday.trim().parse::<Weekday>().ok()?;
+    let target = weekday.num_days_from_monday() as i64;
+    let current = today.weekday().num_days_from_monday() as i64;
 
-    match pattern {
-        Some(pattern) => pattern
-            .captures(date_str)
-            .map(|captures| captures[1].to_string()),
-        None => None,
+    let days = match week_shift {
+        Some(week_shift) => week_shift * 7 + target - current,
+        None => (target - current).rem_euclid(7),
+    };
+    today.checked_add_signed(Duration::days(days))
+}
+
+fn parse_offset(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| Regex::new(r"^([+-])(\d+)([dw])$").expect("valid regex"));
+    let captures = pattern.captures(value)?;
+    let amount = captures[2].parse::<i64>().ok()?;
+    let days = match &captures[3] {
+        "w" => amount.checked_mul(7)?,
+        _ => amount,
+    };
+    let days = if &captures[1] == "-" { -days } else { days };
+    today.checked_add_signed(Duration::try_days(days)?)
+}
+
+// ISO week `2023-W10` means its monday, `2023-W10-5` its friday.
+fn parse_iso_week(value: &str) -> Option<NaiveDate> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN
+        .get_or_init(|| Regex::new(r"^(\d{4})-w(\d{2})(?:-([1-7]))?$").expect("valid regex"));
+    let captures = pattern.captures(value)?;
+    let year = captures[1].parse::<i32>().ok()?;
+    let week = captures[2].parse::<u32>().ok()?;
+    let weekday = match captures.get(3) {
+        Some(day) => Weekday::try_from(day.as_str().parse::<u8>().ok()? - 1).ok()?,
+        None => Weekday::Mon,
+    };
+    NaiveDate::from_isoywd_opt(year, week, weekday)
+}
+
+fn parse_day_month(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let (day, month) = value.split_once('-')?;
+    NaiveDate::from_ymd_opt(today.year(), month.parse().ok()?, day.parse().ok()?)
+}
+
+/// Interval like `30m`, `1h`, `1h30m` or `45s`.
+pub fn parse_interval(value: &str) -> Result<std::time::Duration, &'static str> {
+    let hint = "Can't parse interval, please use: 45s, 30m, 1h or 1h30m";
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| Regex::new(r"(\d+)([smhd])").expect("valid regex"));
+    let value = value.trim().to_lowercase();
+    if value.is_empty() || !pattern.replace_all(&value, "").is_empty() {
+        return Err(hint);
+    }
+    let seconds = pattern
+        .captures_iter(&value)
+        .try_fold(0u64, |total, captures| {
+            let amount = captures[1].parse::<u64>().ok()?;
+            let unit = match &captures[2] {
+                "s" => 1,
+                "m" => 60,
+                "h" => 60 * 60,
+                _ => 24 * 60 * 60,
+            };
+            total.checked_add(amount.checked_mul(unit)?)
+        })
+        .ok_or(hint)?;
+    match seconds {
+        0 => Err(hint),
+        seconds => Ok(std::time::Duration::from_secs(seconds)),
     }
 }
 
-impl ToString for DateOption {
-    fn to_string(&self) -> String {
-        self.date.format("%Y-%m-%d").to_string()
+impl fmt::Display for DateOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.date.format("%Y-%m-%d"))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::{Duration, NaiveDate};
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
 
-    use crate::utils::date::{DateOption, Local};
+    use crate::utils::date::{parse_interval, today_at, DateOption, Local, Zone};
     #[test]
     fn date_for_now() {
         let curr_date = Local::now().date_naive();
         let date_param = date_option_string(curr_date);
-        let date_option = DateOption::new(&date_param);
+        let date_option = DateOption::new(&date_param, None).unwrap();
         assert_date_option(date_option, curr_date, 0)
     }
 
@@ -65,7 +234,7 @@ mod tests {
         let days_ago = -72;
         let date = date_from_now(days_ago);
         let date_param = date_option_string(date);
-        assert_date_option(DateOption::new(&date_param), date, days_ago);
+        assert_date_option(DateOption::new(&date_param, None).unwrap(), date, days_ago);
     }
 
     #[test]
@@ -73,7 +242,11 @@ mod tests {
         let days_in_future = 86;
         let date = date_from_now(days_in_future);
         let date_param = date_option_string(date);
-        assert_date_option(DateOption::new(&date_param), date, days_in_future);
+        assert_date_option(
+            DateOption::new(&date_param, None).unwrap(),
+            date,
+            days_in_future,
+        );
     }
 
     #[test]
@@ -81,7 +254,126 @@ mod tests {
         let days_in_future = 7;
         let date = date_from_now(days_in_future);
         let date_param = date_option_string(date);
-        assert_date_option(DateOption::new(&date_param), date, days_in_future);
+        assert_date_option(
+            DateOption::new(&date_param, None).unwrap(),
+            date,
+            days_in_future,
+        );
+    }
+
+    // 2023-03-01 is wednesday
+    #[test]
+    fn relative_dates() {
+        assert_parsed("today", "2023-03-01", 0);
+        assert_parsed("date=tomorrow", "2023-03-02", 1);
+        assert_parsed("yesterday", "2023-02-28", -1);
+        assert_parsed("+3d", "2023-03-04", 3);
+        assert_parsed("date=-2w", "2023-02-15", -14);
+    }
+
+    #[test]
+    fn huge_offsets_are_errors() {
+        for value in [
+            "-99999999999999d",
+            "+9999999999999998d",
+            "+9999999999999999w",
+        ] {
+            assert!(DateOption::parse(value, today()).is_err());
+        }
+    }
+
+    #[test]
+    fn weekday_dates() {
+        assert_parsed("wednesday", "2023-03-01", 0);
+        assert_parsed("Friday", "2023-03-03", 2);
+        assert_parsed("monday", "2023-03-06", 5);
+        assert_parsed("next monday", "2023-03-06", 5);
+        assert_parsed("next friday", "2023-03-10", 9);
+        assert_parsed("last friday", "2023-02-24", -5);
+    }
+
+    #[test]
+    fn formatted_dates() {
+        assert_parsed("04-03", "2023-03-04", 3);
+        assert_parsed("date=25.02.2023", "2023-02-25", -4);
+        assert_parsed("2023-W10", "2023-03-06", 5);
+        assert_parsed("2023-w09-5", "2023-03-03", 2);
+    }
+
+    #[test]
+    fn unparseable_dates() {
+        for value in ["date=soon", "31-02", "+3y", "2023-W60", "next week"] {
+            assert!(DateOption::parse(value, today()).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn today_in_zone() {
+        let kyiv = Zone::Named(chrono_tz::Europe::Kyiv);
+        let los_angeles = Zone::Named(chrono_tz::America::Los_Angeles);
+        // Early morning in Kyiv is still yesterday evening in Los Angeles.
+        let now = Utc.with_ymd_and_hms(2025, 3, 4, 4, 0, 0).unwrap();
+        assert_eq!(
+            today_at(Some(kyiv), now),
+            NaiveDate::from_ymd_opt(2025, 3, 4).unwrap()
+        );
+        assert_eq!(
+            today_at(Some(los_angeles), now),
+            NaiveDate::from_ymd_opt(2025, 3, 3).unwrap()
+        );
+        let date_option = DateOption::parse("today", today_at(Some(los_angeles), now)).unwrap();
+        assert_eq!(
+            date_option.date,
+            NaiveDate::from_ymd_opt(2025, 3, 3).unwrap()
+        );
+        assert_eq!(date_option.days_from_now, 0);
+    }
+
+    #[test]
+    fn parses_zones() {
+        assert_eq!(
+            "Europe/Kyiv".parse(),
+            Ok(Zone::Named(chrono_tz::Europe::Kyiv))
+        );
+        assert_eq!(
+            "UTC+2".parse(),
+            Ok(Zone::from_offset_seconds(7200).unwrap())
+        );
+        assert_eq!(
+            "-05:30".parse(),
+            Ok(Zone::from_offset_seconds(-19800).unwrap())
+        );
+        assert_eq!(
+            Zone::from_offset_seconds(7200).unwrap().to_string(),
+            "UTC+02:00"
+        );
+        assert!("Mars/Olympus".parse::<Zone>().is_err());
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 3, 1).unwrap()
+    }
+
+    fn assert_parsed(value: &str, date: &str, days_from_now: i64) {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        assert_date_option(
+            DateOption::parse(value, today()).unwrap(),
+            date,
+            days_from_now,
+        );
+    }
+
+    #[test]
+    fn intervals() {
+        let seconds = |value| parse_interval(value).map(|interval| interval.as_secs());
+        assert_eq!(seconds("45s"), Ok(45));
+        assert_eq!(seconds("30m"), Ok(1800));
+        assert_eq!(seconds("1h30m"), Ok(5400));
+        assert_eq!(seconds("1D"), Ok(86400));
+        assert!(seconds("30").is_err());
+        assert!(seconds("0m").is_err());
+        assert!(seconds("30 minutes").is_err());
+        assert!(seconds("").is_err());
     }
 
     fn date_option_string(date: NaiveDate) -> String {
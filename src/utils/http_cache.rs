@@ -0,0 +1,256 @@
+use crate::utils::http::{self, HttpError};
+use chrono::{DateTime, Utc};
+use reqwest::header::{EXPIRES, IF_MODIFIED_SINCE, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/*
+Some services (MET Norway) require clients to honour HTTP caching:
+responses are reused until `Expires`, and afterwards revalidated with `If-Modified-Since`
+using the `Last-Modified` value of the cached response.
+Cached bodies are kept as json files in the user's cache directory.
+*/
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CachedResponse {
+    body: String,
+    last_modified: Option<String>,
+    expires: Option<String>,
+}
+
+impl CachedResponse {
+    fn is_fresh(&self) -> bool {
+        match &self.expires {
+            Some(expires) => match DateTime::parse_from_rfc2822(expires) {
+                Ok(expires) => expires.with_timezone(&Utc) > Utc::now(),
+                Err(_) => false,
+            },
+            None => false,
+        }
+    }
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("rs", "", "weather-cli")
+        .map(|dirs| dirs.cache_dir().to_path_buf())
+}
+
+/// Body of the url, `attempt` runs before every request which isn't answered from the cache.
+pub async fn get<A>(url: &str, attempt: A) -> Result<String, HttpError>
+where
+    A: Fn() -> Result<(), &'static str>,
+{
+    let path = cache_dir().map(|dir| entry_path(&dir, url));
+    get_cached(path.as_deref(), url, attempt).await
+}
+
+/// Body of the url cached at `path`, nothing is cached without it.
+async fn get_cached<A>(path: Option<&Path>, url: &str, attempt: A) -> Result<String, HttpError>
+where
+    A: Fn() -> Result<(), &'static str>,
+{
+    let cached = path.and_then(read_entry);
+    if let Some(cached) = &cached {
+        if cached.is_fresh() {
+            return Ok(cached.body.clone());
+        }
+    }
+
+    let last_modified = cached.as_ref().and_then(|c| c.last_modified.clone());
+    let response = http::send(attempt, |client| {
+        let request = client.get(url);
+        match &last_modified {
+            Some(last_modified) => request.header(IF_MODIFIED_SINCE, last_modified),
+            None => request,
+        }
+    })
+    .await?;
+
+    let expires = header_value(&response, EXPIRES);
+    let last_modified = header_value(&response, LAST_MODIFIED);
+    match (response.status(), cached) {
+        (StatusCode::NOT_MODIFIED, Some(cached)) => {
+            let entry = CachedResponse {
+                body: cached.body,
+                last_modified: last_modified.or(cached.last_modified),
+                expires,
+            };
+            write_entry(path, &entry);
+            Ok(entry.body)
+        }
+        (status, _) if status.is_success() => {
+            let body = match response.text().await {
+                Ok(body) => body,
+                Err(_) => return Err(HttpError::Failed("Can't retrieve weather data")),
+            };
+            let entry = CachedResponse {
+                body,
+                last_modified,
+                expires,
+            };
+            write_entry(path, &entry);
+            Ok(entry.body)
+        }
+        (status, _) => Err(HttpError::Status(
+            status.as_u16(),
+            response.text().await.unwrap_or_default(),
+        )),
+    }
+}
+
+fn header_value(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+fn entry_path(dir: &Path, url: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    dir.join("http")
+        .join(format!("{:016x}.json", hasher.finish()))
+}
+
+fn read_entry(path: &Path) -> Option<CachedResponse> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// Cache is best effort: the forecast is still shown when it can't be written.
+fn write_entry(path: Option<&Path>, entry: &CachedResponse) {
+    if let Some(path) = path {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Ok(content) = serde_json::to_string(entry) {
+            let _ = fs::write(path, content);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_cached, read_entry, write_entry, CachedResponse};
+    use crate::services::executor::block_on;
+    use crate::utils::http::HttpError;
+    use std::cell::Cell;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::thread;
+
+    const LAST_MODIFIED: &str = "Sat, 04 Mar 2023 10:00:00 GMT";
+    const FUTURE: &str = "Fri, 31 Dec 2100 23:59:59 GMT";
+
+    // Answers one request with the response and returns the head of the request.
+    fn stub_server(response: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/compact", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line.to_lowercase());
+            }
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 {response}\r\nConnection: close\r\n"
+            )
+            .unwrap();
+            head
+        });
+        (url, handle)
+    }
+
+    fn entry_at(name: &str, expires: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("weather-cache-{}", std::process::id()))
+            .join(format!("{name}.json"));
+        let entry = CachedResponse {
+            body: "cached".to_string(),
+            last_modified: Some(LAST_MODIFIED.to_string()),
+            expires: Some(expires.to_string()),
+        };
+        write_entry(Some(&path), &entry);
+        path
+    }
+
+    #[test]
+    fn fresh_entry_is_served_without_request() {
+        let path = entry_at("fresh", FUTURE);
+        let attempts = Cell::new(0);
+        let attempt = || {
+            attempts.set(attempts.get() + 1);
+            Err("No request expected")
+        };
+        let body = block_on(get_cached(
+            Some(&path),
+            "http://127.0.0.1:9/compact",
+            attempt,
+        ));
+        assert_eq!(body, Ok("cached".to_string()));
+        assert_eq!(attempts.get(), 0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn stale_entry_is_revalidated() {
+        let path = entry_at("stale", "Sat, 04 Mar 2023 11:00:00 GMT");
+        let (url, server) = stub_server(
+            "200 OK\r\nLast-Modified: Sun, 05 Mar 2023 10:00:00 GMT\r\nContent-Length: 5\r\n\r\nfresh",
+        );
+        let body = block_on(get_cached(Some(&path), &url, || Ok(())));
+        assert_eq!(body, Ok("fresh".to_string()));
+        let head = server.join().unwrap();
+        assert!(head.contains(&format!(
+            "if-modified-since: {}",
+            LAST_MODIFIED.to_lowercase()
+        )));
+        let entry = read_entry(&path).unwrap();
+        assert_eq!(entry.body, "fresh");
+        assert_eq!(
+            entry.last_modified.as_deref(),
+            Some("Sun, 05 Mar 2023 10:00:00 GMT")
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn not_modified_reuses_body_and_refreshes_expiry() {
+        let path = entry_at("not-modified", "Sat, 04 Mar 2023 11:00:00 GMT");
+        let (url, server) =
+            stub_server("304 Not Modified\r\nExpires: Fri, 31 Dec 2100 23:59:59 GMT\r\n\r\n");
+        let body = block_on(get_cached(Some(&path), &url, || Ok(())));
+        assert_eq!(body, Ok("cached".to_string()));
+        server.join().unwrap();
+        let entry = read_entry(&path).unwrap();
+        assert_eq!(entry.expires.as_deref(), Some(FUTURE));
+        assert_eq!(entry.last_modified.as_deref(), Some(LAST_MODIFIED));
+        assert!(entry.is_fresh());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let (url, server) = stub_server("404 Not Found\r\nContent-Length: 2\r\n\r\n{}");
+        let path = std::env::temp_dir()
+            .join(format!("weather-cache-{}", std::process::id()))
+            .join("error.json");
+        let body = block_on(get_cached(Some(&path), &url, || Ok(())));
+        assert_eq!(body, Err(HttpError::Status(404, "{}".to_string())));
+        server.join().unwrap();
+        assert!(read_entry(&path).is_none());
+    }
+}
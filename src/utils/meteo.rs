@@ -0,0 +1,106 @@
+/*
+Small meteorological formulas shared by providers that don't return some values themselves.
+*/
+
+/// Wind speed in km/h of one m/s.
+pub const MPS_TO_KPH: f32 = 3.6;
+/// Depth of fresh snow is about 10 times more than its water equivalent.
+pub const SNOW_PER_LIQUID: f32 = 10.0;
+
+const COMPASS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW",
+    "NNW",
+];
+// Arrow shows where the wind blows to, so wind from the north points down.
+const ARROWS: [&str; 8] = ["↓", "↙", "←", "↖", "↑", "↗", "→", "↘"];
+
+/// Dew point in °C by Magnus formula (Alduchov and Eskridge constants),
+/// good within 0.1°C for -40..50°C.
+pub fn dew_point(temp_c: f32, humidity: f32) -> Option<f32> {
+    if humidity <= 0.0 || humidity > 100.0 {
+        return None;
+    }
+    let (a, b) = (17.625, 243.04);
+    let gamma = (humidity / 100.0).ln() + a * temp_c / (b + temp_c);
+    Some(round(b * gamma / (a - gamma)))
+}
+
+/// Relative humidity in percent for the temperature and dew point, inverse of [`dew_point`].
+pub fn relative_humidity(temp_c: f32, dew_point_c: f32) -> f32 {
+    let (a, b) = (17.625, 243.04);
+    let humidity =
+        100.0 * ((a * dew_point_c / (b + dew_point_c)) - (a * temp_c / (b + temp_c))).exp();
+    round(humidity.min(100.0))
+}
+
+/// 16-point compass name of the direction the wind comes from.
+pub fn compass(degree: f32) -> &'static str {
+    COMPASS[sector(degree, COMPASS.len())]
+}
+
+pub fn wind_arrow(degree: f32) -> &'static str {
+    ARROWS[sector(degree, ARROWS.len())]
+}
+
+/// Mean of directions as vectors, so 350° and 10° average to 0° rather than 180°.
+pub fn mean_direction(degrees: &[f32]) -> Option<f32> {
+    if degrees.is_empty() {
+        return None;
+    }
+    let (sin, cos) = degrees.iter().fold((0.0, 0.0), |(sin, cos), degree| {
+        let radians = degree.to_radians();
+        (sin + radians.sin(), cos + radians.cos())
+    });
+    Some(sin.atan2(cos).to_degrees().round().rem_euclid(360.0))
+}
+
+fn sector(degree: f32, sectors: usize) -> usize {
+    let width = 360.0 / sectors as f32;
+    ((degree.rem_euclid(360.0) + width / 2.0) / width) as usize % sectors
+}
+
+/// Value with one decimal, as providers give them.
+pub fn round(value: f32) -> f32 {
+    (value * 10.0).round() / 10.0
+}
+
+/// Mean with one decimal, `None` without values.
+pub fn average(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(round(values.iter().sum::<f32>() / values.len() as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compass, dew_point, mean_direction, relative_humidity, wind_arrow};
+
+    #[test]
+    fn dew_point_by_magnus_formula() {
+        assert_eq!(dew_point(20.0, 50.0), Some(9.3));
+        assert_eq!(dew_point(30.0, 80.0), Some(26.2));
+        assert_eq!(dew_point(-10.0, 70.0), Some(-14.4));
+        assert_eq!(dew_point(15.0, 100.0), Some(15.0));
+        assert_eq!(dew_point(15.0, 0.0), None);
+        assert!((relative_humidity(20.0, 9.3) - 50.0).abs() <= 0.2);
+        assert_eq!(relative_humidity(15.0, 16.0), 100.0);
+    }
+
+    #[test]
+    fn compass_directions() {
+        assert_eq!(compass(0.0), "N");
+        assert_eq!(compass(355.0), "N");
+        assert_eq!(compass(22.5), "NNE");
+        assert_eq!(compass(225.0), "SW");
+        assert_eq!(wind_arrow(0.0), "↓");
+        assert_eq!(wind_arrow(270.0), "→");
+    }
+
+    #[test]
+    fn mean_direction_around_north() {
+        assert_eq!(mean_direction(&[350.0, 10.0]), Some(0.0));
+        assert_eq!(mean_direction(&[80.0, 100.0]), Some(90.0));
+        assert_eq!(mean_direction(&[]), None);
+    }
+}
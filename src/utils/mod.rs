@@ -1,2 +1,6 @@
 pub mod config;
 pub mod date;
+pub mod http;
+pub mod http_cache;
+pub mod meteo;
+pub mod output;
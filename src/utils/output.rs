@@ -0,0 +1,132 @@
+use clap::ValueEnum;
+use std::env;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+/// How much of the forecast is shown: `basic` is temperature, humidity, wind and precipitation,
+/// `full` adds pressure, UV, visibility, dew point, cloud cover and gusts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Detail {
+    #[default]
+    Basic,
+    Full,
+}
+
+/// Number for tables and csv, empty when the value is missing.
+pub fn optional(value: Option<f32>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "".to_string(),
+    }
+}
+
+/// Quotes csv field when it contains separator, quote or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Symbols of the output: `unicode` has emoji, arrows and blocks, `ascii` only plain characters
+/// for CI logs and consoles without Unicode. `auto` is ascii when stdout isn't a terminal,
+/// TERM is dumb or the locale isn't UTF-8.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Charset {
+    #[default]
+    Auto,
+    Unicode,
+    Ascii,
+}
+
+// Charset is set once at start, `auto` is resolved then.
+static CHARSET: OnceLock<Charset> = OnceLock::new();
+
+pub fn set_charset(charset: Charset) {
+    let _ = CHARSET.set(match charset {
+        Charset::Auto => detect_charset(),
+        charset => charset,
+    });
+}
+
+pub fn unicode() -> bool {
+    *CHARSET.get_or_init(detect_charset) == Charset::Unicode
+}
+
+fn detect_charset() -> Charset {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty());
+    charset_of(
+        std::io::stdout().is_terminal(),
+        env::var("TERM").ok().as_deref(),
+        locale.as_deref(),
+        env::var_os("WT_SESSION").is_some(),
+    )
+}
+
+// Windows consoles have no locale variables, only Windows Terminal is sure to show emoji.
+fn charset_of(
+    terminal: bool,
+    term: Option<&str>,
+    locale: Option<&str>,
+    windows_terminal: bool,
+) -> Charset {
+    let utf8 = |locale: &str| {
+        let locale = locale.to_lowercase();
+        locale.contains("utf-8") || locale.contains("utf8")
+    };
+    match (terminal, term, locale) {
+        (false, _, _) | (_, Some("dumb"), _) => Charset::Ascii,
+        _ if cfg!(windows) && windows_terminal => Charset::Unicode,
+        (_, _, Some(locale)) if utf8(locale) => Charset::Unicode,
+        _ => Charset::Ascii,
+    }
+}
+
+/// Emoji of a line with the space after it, the same width in ascii.
+pub fn icon(emoji: &str) -> String {
+    match unicode() {
+        true => format!("{emoji}  "),
+        false => "-   ".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{charset_of, Charset};
+
+    #[test]
+    fn charset_of_terminal() {
+        assert_eq!(
+            charset_of(true, Some("xterm-256color"), Some("en_US.UTF-8"), false),
+            Charset::Unicode
+        );
+        assert_eq!(
+            charset_of(true, Some("xterm"), Some("C.utf8"), false),
+            Charset::Unicode
+        );
+        assert_eq!(
+            charset_of(false, Some("xterm"), Some("en_US.UTF-8"), false),
+            Charset::Ascii
+        );
+        assert_eq!(
+            charset_of(true, Some("dumb"), Some("en_US.UTF-8"), false),
+            Charset::Ascii
+        );
+        assert_eq!(
+            charset_of(true, Some("xterm"), Some("POSIX"), false),
+            Charset::Ascii
+        );
+        assert_eq!(charset_of(true, None, None, false), Charset::Ascii);
+    }
+}